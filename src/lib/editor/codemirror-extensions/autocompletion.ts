@@ -68,21 +68,21 @@ export function createAutocompletion(getContext: () => PluginContext) {
           }
         }
 
+        const otherTags = tokenRanges
+          .filter(
+            (token) =>
+              token !== currentToken &&
+              token.token != null &&
+              token.token.type !== "chant_marker",
+          )
+          .map((range) => range.token)
+          .filter((v): v is Tag => v != null);
         const existingTags = new Set(
-          tokenRanges
-            .filter(
-              (token) =>
-                token !== currentToken &&
-                token.token != null &&
-                token.token.type !== "chant_marker",
-            )
-            .map((range) => range.token)
-            .filter((v): v is Tag => v != null)
-            .map((v) =>
-              v.type === "normal"
-                ? `${v.type}\0${normalizeForQuery(v.name)}`
-                : `${v.type}\0${v.name}`,
-            ),
+          otherTags.map((v) =>
+            v.type === "normal"
+              ? `${v.type}\0${normalizeForQuery(v.name)}`
+              : `${v.type}\0${v.name}`,
+          ),
         );
 
         let start = pos - line.from;
@@ -205,31 +205,22 @@ export function createAutocompletion(getContext: () => PluginContext) {
           };
         }
 
+        // Tags already in the prompt are ranked lower by the engine
         const searchResults = await dictionaryEngine?.fuzzy_search(
           currentTag,
           MAX_SUGGESTIONS,
+          undefined,
+          otherTags.filter((v) => v.type === "normal").map((v) => v.name),
         );
         if (!searchResults) {
           console.warn("Dictionary engine not available");
           return null;
         }
 
-        const completions: Completion[] = searchResults
-          .map((result): Completion & { score: number } => {
-            const existing = existingTags.has(
-              `normal\0${normalizeForQuery(result.canonical_key)}`,
-            );
-
-            let scoreOffset = 0;
-            if (result.term === currentTag.trim()) {
-              scoreOffset = 1000;
-            } else if (existing) {
-              scoreOffset = -20;
-            } else if (!result.is_canonical) {
-              scoreOffset = -10;
-            }
-
-            const score = result.score + scoreOffset;
+        // Results come ranked by the engine, and are shown in that order
+        const completions: Completion[] = searchResults.map(
+          (result): Completion => {
+            const existing = result.is_present;
             const aliasesWithoutCurrent = result.aliases.filter(
               (alias) => alias !== currentTag.trim(),
             );
@@ -247,10 +238,9 @@ export function createAutocompletion(getContext: () => PluginContext) {
               ].join(" "),
               apply: `${normalizeForCompletion(result.canonical_key)}${missingSuffix}, `,
               type: `normal-${categoryToId(result.category)}-${existing ? "existing" : "new"}`,
-              score,
             };
-          })
-          .sort((a, b) => (b.score ?? 0) - (a.score ?? 0));
+          },
+        );
 
         return {
           from: replaceStart,
@@ -8,6 +8,7 @@ export interface IDictionaryEngine {
     query: string,
     maxEntries?: number,
     forceNonAscii?: boolean,
+    presentTags?: readonly string[],
   ): Promise<CompletionResultEntry[]> | CompletionResultEntry[];
 
   query_words(
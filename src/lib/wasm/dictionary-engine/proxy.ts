@@ -106,12 +106,14 @@ export class DictionaryEngineWorkerProxy implements IDictionaryEngine {
     query: string,
     maxEntries?: number,
     forceNonAscii?: boolean,
+    presentTags?: readonly string[],
   ): Promise<CompletionResultEntry[]> {
     await this.#initPromise;
     return this.sendMessage<CompletionResultEntry[]>("fuzzy_search", {
       query,
       maxEntries,
       forceNonAscii,
+      presentTags,
     });
   }
 
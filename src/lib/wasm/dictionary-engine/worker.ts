@@ -9,6 +9,7 @@ interface FuzzySearchData {
   query: string;
   maxEntries?: number;
   forceNonAscii?: boolean;
+  presentTags?: readonly string[];
 }
 
 interface QueryWordsData {
@@ -69,12 +70,14 @@ self.addEventListener("message", async (event: MessageEvent<WorkerMessage>) => {
           throw new Error("Missing data for fuzzy_search");
         }
 
-        const { query, maxEntries, forceNonAscii } = data as FuzzySearchData;
-        const results = dictionaryEngine.fuzzy_search(
-          query,
-          maxEntries,
-          forceNonAscii,
-        );
+        const { query, maxEntries, forceNonAscii, presentTags } =
+          data as FuzzySearchData;
+        const results = dictionaryEngine.fuzzy_search_with_options(query, {
+          max_entries: maxEntries,
+          force_try_non_ascii: forceNonAscii,
+          present_tags: presentTags as string[] | undefined,
+          present_tag_mode: "demote",
+        });
 
         const response: WorkerResponse = {
           id,
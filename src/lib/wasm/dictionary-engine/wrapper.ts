@@ -21,9 +21,15 @@ export class DictionaryEngineWrapper implements IDictionaryEngine {
     query: string,
     maxEntries?: number,
     forceNonAscii?: boolean,
+    presentTags?: readonly string[],
   ): Promise<CompletionResultEntry[]> {
     const engine = await this.#enginePromise;
-    return engine.fuzzy_search(query, maxEntries, forceNonAscii);
+    return engine.fuzzy_search_with_options(query, {
+      max_entries: maxEntries,
+      force_try_non_ascii: forceNonAscii,
+      present_tags: presentTags as string[] | undefined,
+      present_tag_mode: "demote",
+    });
   }
 
   async query_words(words: readonly string[]): Promise<QueryResultEntry[]> {
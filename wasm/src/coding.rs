@@ -1,7 +1,77 @@
 use base64::{Engine, prelude::BASE64_STANDARD_NO_PAD};
-use std::io::Write;
+use std::io::{Read, Write};
+use tsify::Tsify;
 use wasm_bindgen::prelude::wasm_bindgen;
 
+// How a binary dictionary payload is encoded
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Tsify, serde::Serialize, serde::Deserialize,
+)]
+#[tsify(from_wasm_abi)]
+#[serde(rename_all = "kebab-case")]
+pub enum PayloadEncoding {
+    // Payloads starting with "BR-" are read as `encode_text` output, valid UTF-8 as plain text,
+    // and anything else as raw Brotli. Very short Brotli streams can be valid UTF-8, so pass
+    // `Brotli` explicitly when the encoding is known.
+    #[default]
+    Auto,
+    Plain,
+    Brotli,
+    // The "BR-" prefixed Base64 format produced by `encode_text`
+    Base64Brotli,
+}
+
+// Decompresses Brotli data, failing once the output would exceed `max_size` bytes
+fn decompress_brotli(compressed: &[u8], max_size: usize) -> Result<Vec<u8>, String> {
+    let mut decompressed = Vec::new();
+    brotli::Decompressor::new(compressed, 4096)
+        .take((max_size as u64).saturating_add(1))
+        .read_to_end(&mut decompressed)
+        .map_err(|err| format!("Failed to decompress payload: {err}"))?;
+
+    if decompressed.len() > max_size {
+        return Err(format!(
+            "Decompressed payload exceeds the limit of {max_size} bytes"
+        ));
+    }
+    Ok(decompressed)
+}
+
+// Turns a dictionary payload into text, decompressing it if needed
+pub(crate) fn decode_payload(
+    payload: &[u8],
+    encoding: PayloadEncoding,
+    max_size: usize,
+) -> Result<String, String> {
+    let encoding = match encoding {
+        PayloadEncoding::Auto if payload.starts_with(b"BR-") => PayloadEncoding::Base64Brotli,
+        PayloadEncoding::Auto if std::str::from_utf8(payload).is_ok() => PayloadEncoding::Plain,
+        PayloadEncoding::Auto => PayloadEncoding::Brotli,
+        encoding => encoding,
+    };
+
+    let bytes = match encoding {
+        PayloadEncoding::Plain => {
+            if payload.len() > max_size {
+                return Err(format!("Payload exceeds the limit of {max_size} bytes"));
+            }
+            payload.to_vec()
+        }
+        PayloadEncoding::Brotli => decompress_brotli(payload, max_size)?,
+        PayloadEncoding::Base64Brotli | PayloadEncoding::Auto => {
+            let stripped = payload
+                .strip_prefix(b"BR-")
+                .ok_or_else(|| "Invalid encoded text format".to_string())?;
+            let compressed = BASE64_STANDARD_NO_PAD
+                .decode(stripped)
+                .map_err(|err| format!("Failed to decode Base64: {err}"))?;
+            decompress_brotli(&compressed, max_size)?
+        }
+    };
+
+    String::from_utf8(bytes).map_err(|err| format!("Payload is not valid UTF-8: {err}"))
+}
+
 #[wasm_bindgen]
 pub fn encode_text(text: String) -> Result<String, String> {
     // Brotli compression → Base64 encoding → Prefix with "BR-"
@@ -134,6 +204,55 @@ mod tests {
         );
     }
 
+    fn compress(text: &str) -> Vec<u8> {
+        let mut writer = brotli::CompressorWriter::new(Vec::new(), 4096, 11, 22);
+        writer.write_all(text.as_bytes()).unwrap();
+        writer.into_inner()
+    }
+
+    #[test]
+    fn test_decode_payload_detection() {
+        let text = "1girl,0,100,\"girl,女の子\"\n".repeat(20);
+
+        let plain = decode_payload(text.as_bytes(), PayloadEncoding::Auto, usize::MAX).unwrap();
+        assert_eq!(plain, text);
+
+        let brotli = decode_payload(&compress(&text), PayloadEncoding::Auto, usize::MAX).unwrap();
+        assert_eq!(brotli, text);
+
+        let encoded = encode_text(text.clone()).unwrap();
+        let base64 = decode_payload(encoded.as_bytes(), PayloadEncoding::Auto, usize::MAX).unwrap();
+        assert_eq!(base64, text);
+
+        // An explicit encoding skips detection
+        let explicit = decode_payload(&compress("a"), PayloadEncoding::Brotli, usize::MAX).unwrap();
+        assert_eq!(explicit, "a");
+    }
+
+    #[test]
+    fn test_decode_payload_size_limit() {
+        let text = "a".repeat(100_000);
+        let compressed = compress(&text);
+        assert!(compressed.len() < 1000);
+
+        let error = decode_payload(&compressed, PayloadEncoding::Brotli, 1000).unwrap_err();
+        assert!(error.contains("exceeds the limit"));
+        assert!(decode_payload(&compressed, PayloadEncoding::Brotli, 100_000).is_ok());
+
+        let error = decode_payload(text.as_bytes(), PayloadEncoding::Plain, 1000).unwrap_err();
+        assert!(error.contains("exceeds the limit"));
+    }
+
+    #[test]
+    fn test_decode_payload_invalid() {
+        let error =
+            decode_payload(b"\xff\xfe\x00", PayloadEncoding::Plain, usize::MAX).unwrap_err();
+        assert!(error.contains("not valid UTF-8"));
+
+        let error = decode_payload(b"not brotli", PayloadEncoding::Brotli, usize::MAX).unwrap_err();
+        assert!(error.contains("Failed to decompress payload"));
+    }
+
     #[test]
     fn test_compression_effectiveness() {
         // Test that compression is effective for repetitive text
@@ -1,7 +1,7 @@
 use std::collections::{HashMap, HashSet};
 
 use nucleo_matcher::{
-    Config, Matcher,
+    Config, Matcher, Utf32Str,
     pattern::{CaseMatching, Normalization, Pattern},
 };
 #[cfg(not(target_arch = "wasm32"))]
@@ -11,7 +11,55 @@ use wasm_bindgen::prelude::*;
 #[cfg(target_arch = "wasm32")]
 use web_time::{Duration, Instant};
 
-use crate::normalize::{normalize_for_auto_completion, normalize_for_query};
+use crate::hangul::to_initial_consonants;
+use crate::hanzi::to_pinyin;
+use crate::normalize::{
+    normalize_for_auto_completion, normalize_for_auto_completion_with_offsets, normalize_for_query,
+};
+use crate::romaji::romaji_variants;
+
+mod analysis;
+mod budget;
+mod builder;
+mod canonicalize;
+mod duplicates;
+mod formats;
+mod fragments;
+mod grouped;
+mod incremental;
+mod localize;
+mod modes;
+mod payload;
+mod query_syntax;
+mod ranking;
+mod report;
+mod search_cache;
+mod snapshot;
+mod suggestions;
+mod typo;
+
+pub use analysis::PromptSpan;
+pub use budget::FuzzySearchResponse;
+use budget::{GenerationSignal, Interruption, SearchBudget, deadline_after};
+pub use builder::{BuildPhase, BuildProgress, DictionaryEngineBuilder};
+pub use canonicalize::{
+    AmbiguousPromptTag, CanonicalizeOptions, CanonicalizedPrompt, PromptReplacement, TagNameStyle,
+};
+pub use duplicates::DuplicateMergePolicy;
+use duplicates::{DuplicateGroup, merge_duplicates};
+use formats::parse_source;
+pub use formats::{DictionaryFormat, DictionaryFormatKind};
+pub use fragments::FragmentQueryResult;
+pub use grouped::{GroupedCompletionResultEntry, GroupedFuzzySearchResponse};
+pub use localize::{LocalizedPrompt, PromptScript};
+pub use modes::SearchMode;
+use query_syntax::parse_query;
+pub use ranking::RankingWeights;
+use ranking::{MatchKind, RankedMatch, sort_ranked_matches};
+pub use report::{LoadReport, SkipReason, SkippedLine, SourceLoadReport};
+use search_cache::{SearchCache, match_haystacks};
+pub use suggestions::{QuerySuggestion, SuggestedQueryResultEntry};
+use typo::DEFAULT_TYPO_FALLBACK_THRESHOLD;
 
 // Performance logging helper
 fn log_performance(operation: &str, duration: Duration, details: Option<&str>) {
@@ -40,20 +88,139 @@ fn log_warning(message: &str) {
     println!("[DictionaryEngine] WARNING: {message}");
 }
 
-#[derive(Debug, serde::Deserialize)]
-pub struct BaseCsvEntry {
-    pub key: String,
-    pub category: i32,
-    pub count: i32,
-    pub aliases: Option<String>,
-}
-
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct DictionaryEntry {
     pub key: String,
     pub category: i32,
     pub count: i32,
     pub aliases: Vec<String>,
+    pub source: String,
+    pub priority: i32,
+    // Set on duplicates whose data was merged into an earlier entry with the same key.
+    // Shadowed entries are kept in place but never indexed.
+    pub shadowed: bool,
+}
+
+impl DictionaryEntry {
+    // Iterates over the canonical key followed by all aliases
+    fn terms(&self) -> impl Iterator<Item = (&String, Option<usize>)> {
+        std::iter::once((&self.key, None))
+            .chain(self.aliases.iter().enumerate().map(|(i, s)| (s, Some(i))))
+    }
+}
+
+#[derive(Debug, Clone, Default, Tsify, serde::Deserialize)]
+#[tsify(from_wasm_abi)]
+pub struct DictionarySourceInput {
+    pub id: String,
+    // Higher priority entries rank ahead of lower priority ones regardless of count. In fuzzy search
+    // results, each level adds `RankingWeights::priority` to the rank.
+    #[serde(default)]
+    #[tsify(optional)]
+    pub priority: i32,
+    pub text: String,
+    #[serde(default)]
+    #[tsify(optional)]
+    pub format: DictionaryFormat,
+}
+
+#[derive(Debug, Clone, Default, Tsify, serde::Serialize, serde::Deserialize)]
+#[tsify(from_wasm_abi)]
+#[serde(default)]
+pub struct DictionaryEngineOptions {
+    #[tsify(optional)]
+    pub merge_policy: DuplicateMergePolicy,
+    // Upper bound in bytes for a decoded binary payload, `DEFAULT_MAX_PAYLOAD_SIZE` if unset
+    #[tsify(optional)]
+    pub max_payload_size: Option<usize>,
+    #[tsify(optional)]
+    pub ranking: RankingWeights,
+    #[tsify(optional)]
+    pub secondary_keys: SecondaryKeyOptions,
+}
+
+// Extra completion keys indexed for each term, so that it can be found by typing a transliteration
+#[derive(Debug, Clone, Default, PartialEq, Eq, Tsify, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SecondaryKeyOptions {
+    // Hepburn and Kunrei romaji of kana terms
+    #[tsify(optional)]
+    pub romaji: bool,
+    // Toneless pinyin of Han terms, e.g. `jinfa` for `金发`
+    #[tsify(optional)]
+    pub pinyin: bool,
+    // Initial consonants of Hangul terms, e.g. `ㄷㅂ` for `단발`
+    #[tsify(optional)]
+    pub hangul_initials: bool,
+}
+
+// What happens to results for tags that are already in the prompt
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Tsify, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PresentTagMode {
+    #[default]
+    Exclude,
+    // Keep them, lowering their rank by `RankingWeights::present_tag_penalty`
+    Demote,
+}
+
+#[derive(Debug, Clone, Default, Tsify, serde::Deserialize)]
+#[tsify(from_wasm_abi)]
+#[serde(default)]
+pub struct FuzzySearchOptions {
+    #[tsify(optional)]
+    pub max_entries: Option<usize>,
+    #[tsify(optional)]
+    pub force_try_non_ascii: Option<bool>,
+    // Only return entries in these categories
+    #[tsify(optional)]
+    pub categories: Option<Vec<i32>>,
+    #[tsify(optional)]
+    pub exclude_categories: Option<Vec<i32>>,
+    #[tsify(optional)]
+    pub min_count: Option<i32>,
+    // Skip completion keys containing any of these terms, compared after normalization
+    #[tsify(optional)]
+    pub exclude_terms: Option<Vec<String>>,
+    // Fill `match_indices` on each result
+    #[tsify(optional)]
+    pub include_match_indices: bool,
+    // Tags already in the prompt, as canonical keys or aliases
+    #[tsify(optional)]
+    pub present_tags: Option<Vec<String>>,
+    #[tsify(optional)]
+    pub present_tag_mode: PresentTagMode,
+    // Typo corrections are added when fewer results than this pass the filters,
+    // `DEFAULT_TYPO_FALLBACK_THRESHOLD` if unset. 0 disables them.
+    #[tsify(optional)]
+    pub typo_fallback_threshold: Option<usize>,
+    // How the query is matched. Typo corrections are only added in fuzzy mode.
+    #[tsify(optional)]
+    pub mode: SearchMode,
+    // Time budget in milliseconds for fuzzy matching, checked between chunks of keys rather than per
+    // key. Past it, the keys matched so far are ranked and the response is flagged as truncated.
+    // Budgets too large to represent mean no deadline.
+    #[tsify(optional)]
+    pub deadline_ms: Option<f64>,
+    // Id of the request, increasing with each request. Searches older than the newest seen are aborted,
+    // including running ones once the generation signal moves past them.
+    #[tsify(optional)]
+    pub generation: Option<u32>,
+}
+
+impl FuzzySearchOptions {
+    fn accepts(&self, entry: &DictionaryEntry) -> bool {
+        self.categories
+            .as_ref()
+            .is_none_or(|categories| categories.contains(&entry.category))
+            && self
+                .exclude_categories
+                .as_ref()
+                .is_none_or(|categories| !categories.contains(&entry.category))
+            && self
+                .min_count
+                .is_none_or(|min_count| entry.count >= min_count)
+    }
 }
 
 #[derive(Debug, Clone, Tsify, serde::Serialize)]
@@ -64,8 +231,20 @@ pub struct CompletionResultEntry {
     pub is_canonical: bool,
     pub category: i32,
     pub count: i32,
+    // Raw nucleo score
     pub score: u32,
+    // Combined score from the ranking model, which results are sorted by
+    pub rank: f64,
+    // Whether the tag is one of the `present_tags` of the search
+    pub is_present: bool,
+    // Whether the term was found by the typo-tolerant fallback rather than fuzzy matching
+    pub is_typo_correction: bool,
     pub aliases: Vec<String>,
+    pub source: String,
+    // Char offsets into `term` of the matched characters, if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[tsify(optional)]
+    pub match_indices: Option<Vec<u32>>,
 }
 
 #[derive(Debug, Tsify, serde::Serialize)]
@@ -77,13 +256,14 @@ pub struct QueryResultEntryValue {
     pub category: i32,
     pub count: i32,
     pub aliases: Vec<String>,
+    pub source: String,
 }
 
 #[derive(Debug, Tsify, serde::Serialize)]
 #[tsify(into_wasm_abi)]
 pub struct QueryResultEntry(pub String, pub Vec<QueryResultEntryValue>);
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct IndexEntry {
     pub index: usize,
     pub alias_index: Option<usize>, // None if it's the canonical entry
@@ -93,15 +273,17 @@ pub struct IndexEntry {
 pub struct ScoreableEntry {
     pub index: usize,
     pub is_canonical: bool,
+    pub priority: i32,
     pub count: i64,
 }
 
 impl Ord for ScoreableEntry {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        // Sort by count descending, is_canonical descending (true comes first), then index ascending
+        // Sort by priority descending, count descending, is_canonical descending (true comes first), then index ascending
         other
-            .count
-            .cmp(&self.count)
+            .priority
+            .cmp(&self.priority)
+            .then_with(|| other.count.cmp(&self.count))
             .then_with(|| other.is_canonical.cmp(&self.is_canonical))
             .then_with(|| self.index.cmp(&other.index))
     }
@@ -113,26 +295,269 @@ impl PartialOrd for ScoreableEntry {
     }
 }
 
+// Map keys modified by an incremental update, whose lists and haystack positions need refreshing
+#[derive(Debug, Default)]
+struct TouchedKeys {
+    completion: HashSet<String>,
+    query: HashSet<String>,
+}
+
+// The completion keys of a term: its normalized form, followed by the enabled secondary keys.
+// Secondary keys are partitioned into the haystacks like any other key, so ASCII transliterations
+// are found by ASCII queries without searching the non-ASCII haystack.
+fn completion_keys(term: &str, secondary_keys: &SecondaryKeyOptions) -> Vec<String> {
+    let completion_key = normalize_for_auto_completion(term);
+    let mut secondary = Vec::new();
+    if secondary_keys.romaji {
+        secondary.extend(romaji_variants(&completion_key));
+    }
+    if secondary_keys.pinyin {
+        secondary.extend(to_pinyin(&completion_key));
+    }
+    if secondary_keys.hangul_initials {
+        // Normalized so that the jamo match typed queries, which are normalized the same way
+        secondary.extend(
+            to_initial_consonants(&completion_key).map(|k| normalize_for_auto_completion(&k)),
+        );
+    }
+
+    let mut keys = vec![completion_key];
+    for key in secondary {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    keys
+}
+
+// Registers the canonical key and all aliases of an entry in both maps
+fn index_entry(
+    entry: &DictionaryEntry,
+    index: usize,
+    completion_map: &mut HashMap<String, Vec<IndexEntry>>,
+    query_map: &mut HashMap<String, Vec<IndexEntry>>,
+    secondary_keys: &SecondaryKeyOptions,
+    mut touched: Option<&mut TouchedKeys>,
+) {
+    for (key, alias_index) in entry.terms() {
+        let entry = IndexEntry { index, alias_index };
+
+        // Auto completion
+        for completion_key in completion_keys(key, secondary_keys) {
+            if let Some(touched) = touched.as_deref_mut() {
+                touched.completion.insert(completion_key.clone());
+            }
+            completion_map
+                .entry(completion_key)
+                .or_default()
+                .push(entry);
+        }
+
+        // Query map
+        let query_key = normalize_for_query(key);
+        if let Some(touched) = touched.as_deref_mut() {
+            touched.query.insert(query_key.clone());
+        }
+        query_map.entry(query_key).or_default().push(entry);
+    }
+}
+
+// Removes the canonical key and all aliases of an entry from both maps, dropping keys left empty
+fn unindex_entry(
+    entry: &DictionaryEntry,
+    index: usize,
+    completion_map: &mut HashMap<String, Vec<IndexEntry>>,
+    query_map: &mut HashMap<String, Vec<IndexEntry>>,
+    secondary_keys: &SecondaryKeyOptions,
+    touched: &mut TouchedKeys,
+) {
+    for (key, _) in entry.terms() {
+        for completion_key in completion_keys(key, secondary_keys) {
+            if let Some(indices) = completion_map.get_mut(&completion_key) {
+                indices.retain(|e| e.index != index);
+                if indices.is_empty() {
+                    completion_map.remove(&completion_key);
+                }
+            }
+            touched.completion.insert(completion_key);
+        }
+
+        let query_key = normalize_for_query(key);
+        if let Some(indices) = query_map.get_mut(&query_key) {
+            indices.retain(|e| e.index != index);
+            if indices.is_empty() {
+                query_map.remove(&query_key);
+            }
+        }
+        touched.query.insert(query_key);
+    }
+}
+
+fn sort_index_entries(dictionary: &[DictionaryEntry], indices: &mut [IndexEntry]) {
+    indices.sort_by_key(|v| {
+        (
+            ScoreableEntry {
+                index: v.index,
+                is_canonical: v.alias_index.is_none(),
+                priority: dictionary[v.index].priority,
+                count: dictionary[v.index].count as i64,
+            },
+            v.alias_index,
+        )
+    });
+}
+
+// The haystack rank of a completion key, aggregated over every entry it points to
+fn completion_key_score(dictionary: &[DictionaryEntry], indices: &[IndexEntry]) -> ScoreableEntry {
+    ScoreableEntry {
+        index: indices.iter().map(|e| e.index).min().unwrap_or(usize::MAX),
+        is_canonical: indices.iter().any(|e| e.alias_index.is_none()),
+        priority: indices
+            .iter()
+            .map(|e| dictionary[e.index].priority)
+            .max()
+            .unwrap_or(0),
+        count: indices
+            .iter()
+            .map(|entry| dictionary[entry.index].count as i64)
+            .sum(),
+    }
+}
+
+fn score_completion_keys(
+    keys: impl IntoIterator<Item = String>,
+    dictionary: &[DictionaryEntry],
+    completion_map: &HashMap<String, Vec<IndexEntry>>,
+) -> Vec<(ScoreableEntry, String)> {
+    keys.into_iter()
+        .map(|key| {
+            let score = completion_key_score(dictionary, &completion_map[&key]);
+            (score, key)
+        })
+        .collect()
+}
+
+// Sorts a haystack by score, using the key itself as a final tiebreaker so that the order is deterministic
+fn sort_haystack(
+    haystack: &mut Vec<String>,
+    dictionary: &[DictionaryEntry],
+    completion_map: &HashMap<String, Vec<IndexEntry>>,
+) {
+    let mut scored = score_completion_keys(haystack.drain(..), dictionary, completion_map);
+    scored.sort_unstable();
+    haystack.extend(scored.into_iter().map(|(_, key)| key));
+}
+
+// Merges keys into an already sorted haystack, keeping the same order `sort_haystack` would produce
+fn merge_into_haystack(
+    haystack: &mut Vec<String>,
+    keys: Vec<String>,
+    dictionary: &[DictionaryEntry],
+    completion_map: &HashMap<String, Vec<IndexEntry>>,
+) {
+    if keys.is_empty() {
+        return;
+    }
+
+    let mut additions = score_completion_keys(keys, dictionary, completion_map);
+    additions.sort_unstable();
+
+    let existing = std::mem::take(haystack);
+    haystack.reserve(existing.len() + additions.len());
+
+    let mut additions = additions.into_iter().peekable();
+    for key in existing {
+        let score = (completion_key_score(dictionary, &completion_map[&key]), key);
+        while let Some(addition) = additions.next_if(|addition| *addition < score) {
+            haystack.push(addition.1);
+        }
+        haystack.push(score.1);
+    }
+    haystack.extend(additions.map(|(_, key)| key));
+}
+
+// Merges keys into the lexicographically sorted key index
+fn merge_into_key_index(index: &mut Vec<String>, mut keys: Vec<String>) {
+    if keys.is_empty() {
+        return;
+    }
+    keys.sort_unstable();
+
+    let existing = std::mem::take(index);
+    index.reserve(existing.len() + keys.len());
+
+    let mut keys = keys.into_iter().peekable();
+    for key in existing {
+        while let Some(addition) = keys.next_if(|addition| *addition < key) {
+            index.push(addition);
+        }
+        index.push(key);
+    }
+    index.extend(keys);
+}
+
+// Char offsets into `term` of the characters matched by the pattern.
+// The term is normalized the same way as the haystack, so that offsets can be mapped back.
+fn match_indices(pattern: &Pattern, term: &str, matcher: &mut Matcher) -> Vec<u32> {
+    let (normalized, offsets) = normalize_for_auto_completion_with_offsets(term);
+    let ascii;
+    let haystack = if normalized.iter().all(char::is_ascii) {
+        ascii = normalized.iter().collect::<String>();
+        Utf32Str::Ascii(ascii.as_bytes())
+    } else {
+        Utf32Str::Unicode(&normalized)
+    };
+
+    let mut indices = Vec::new();
+    pattern.indices(haystack, matcher, &mut indices);
+
+    let mut indices = indices
+        .into_iter()
+        .filter_map(|i| offsets.get(i as usize).copied())
+        .collect::<Vec<_>>();
+    indices.sort_unstable();
+    indices.dedup();
+    indices
+}
+
+// A named group of dictionary entries. Entries of each source are stored contiguously in
+// `DictionaryEngine::dictionary`, in the order the sources were added.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct DictionarySource {
+    id: String,
+    len: usize,
+    total_lines: usize,
+    skipped_lines: Vec<SkippedLine>,
+}
+
 #[wasm_bindgen]
 pub struct DictionaryEngine {
     dictionary: Vec<DictionaryEntry>,
+    sources: Vec<DictionarySource>,
+    // Groups of rows sharing a key under a merging `DuplicateMergePolicy`, keyed by `normalize_for_query`
+    duplicates: HashMap<String, DuplicateGroup>,
+    options: DictionaryEngineOptions,
     completion_haystack_ascii: Vec<String>,
     completion_haystack_non_ascii: Vec<String>,
+    // Every completion key in lexicographic order, for prefix search
+    completion_key_index: Vec<String>,
     completion_map: HashMap<String, Vec<IndexEntry>>,
     query_map: HashMap<String, Vec<IndexEntry>>,
     nucleo_matcher: Matcher,
+    search_cache: Option<SearchCache>,
+    // Newest search generation seen
+    search_generation: u32,
+    generation_signal: Option<GenerationSignal>,
 }
 
 #[wasm_bindgen]
 impl DictionaryEngine {
     fn create_completion_result_entry(
         dictionary: &[DictionaryEntry],
-        index: usize,
-        alias_index: Option<usize>,
-        score: u32,
+        ranked: &RankedMatch,
     ) -> CompletionResultEntry {
-        let entry = &dictionary[index];
-        let (term, is_canonical) = match alias_index {
+        let entry = &dictionary[ranked.index];
+        let (term, is_canonical) = match ranked.alias_index {
             Some(alias_index) => (entry.aliases[alias_index].clone(), false),
             None => (entry.key.clone(), true),
         };
@@ -142,275 +567,387 @@ impl DictionaryEngine {
             is_canonical,
             category: entry.category,
             count: entry.count,
-            score,
+            score: ranked.score,
+            rank: ranked.rank,
+            is_present: ranked.is_present,
+            is_typo_correction: ranked.is_typo_correction,
             aliases: entry.aliases.clone(),
+            source: entry.source.clone(),
+            match_indices: None,
         }
     }
 
-    #[wasm_bindgen(constructor)]
-    pub fn new(base_csvs: Vec<String>) -> DictionaryEngine {
-        let start_time = Instant::now();
-
-        let mut dictionary_index = 0;
-        let mut dictionary: Vec<DictionaryEntry> = Vec::new();
-        let mut completion_map: HashMap<String, Vec<IndexEntry>> = HashMap::new();
-        let mut completion_haystack_ascii_set: HashSet<String> = HashSet::new();
-        let mut completion_haystack_non_ascii_set: HashSet<String> = HashSet::new();
-        let mut query_map: HashMap<String, Vec<IndexEntry>> = HashMap::new();
-
-        // Phase 1: CSV parsing and dictionary building
-        let parse_start = Instant::now();
-        let mut skipped_lines = 0;
-        let mut total_lines = 0;
-
-        for csv in base_csvs {
-            // Skip completely empty CSV strings
-            if csv.trim().is_empty() {
+    // Resolves tags to the dictionary indices of their entries. A tag matching canonical keys
+    // resolves to those entries only, otherwise to the entries it is an alias of.
+    fn resolve_present_tags(&self, tags: &[String]) -> HashSet<usize> {
+        let mut present = HashSet::new();
+        for tag in tags {
+            let Some(indices) = self.query_map.get(&normalize_for_query(tag.trim())) else {
+                continue;
+            };
+            let canonical = indices
+                .iter()
+                .filter(|e| e.alias_index.is_none())
+                .collect::<Vec<_>>();
+            if !canonical.is_empty() {
+                present.extend(canonical.iter().map(|e| e.index));
                 continue;
             }
 
-            let mut reader = csv::ReaderBuilder::new()
-                .has_headers(false)
-                .from_reader(csv.as_bytes());
-
-            for result in reader.deserialize() {
-                total_lines += 1;
+            // Resolve aliases to every entry sharing the canonical key they point to
+            for &IndexEntry { index, .. } in indices {
+                let key = normalize_for_query(&self.dictionary[index].key);
+                present.extend(
+                    self.query_map
+                        .get(&key)
+                        .iter()
+                        .copied()
+                        .flatten()
+                        .filter(|e| e.alias_index.is_none())
+                        .map(|e| e.index),
+                );
+            }
+        }
+        present
+    }
 
-                let entry: BaseCsvEntry = match result {
-                    Ok(entry) => entry,
-                    Err(err) => {
-                        skipped_lines += 1;
-                        log_warning(&format!("Failed to parse CSV line {total_lines}: {err}"));
-                        continue; // Skip this line and continue processing
-                    }
-                };
+    fn create_nucleo_matcher() -> Matcher {
+        let mut config = Config::DEFAULT;
+        config.prefer_prefix = false;
 
-                let key = entry.key.trim().to_string();
-                if key.is_empty() {
-                    skipped_lines += 1;
-                    continue; // Skip empty keys
-                }
+        Matcher::new(config)
+    }
 
-                let aliases: Vec<String> = entry
-                    .aliases
-                    .as_ref()
-                    .iter()
-                    .flat_map(|s| s.split(','))
-                    .map(str::trim)
-                    .filter(|s| !s.is_empty())
-                    .map(str::to_string)
-                    .collect();
-                let dictionary_entry = DictionaryEntry {
-                    key: key.clone(),
-                    category: entry.category,
-                    count: entry.count,
-                    aliases: aliases.clone(),
-                };
-                dictionary.push(dictionary_entry);
-
-                for (key, alias_index) in std::iter::once((&key, None))
-                    .chain(aliases.iter().enumerate().map(|(i, s)| (s, Some(i))))
-                {
-                    let entry = IndexEntry {
-                        index: dictionary_index,
-                        alias_index,
-                    };
-
-                    // Auto completion
-                    let completion_key = normalize_for_auto_completion(key);
-                    completion_map
-                        .entry(completion_key.clone())
-                        .or_default()
-                        .push(entry);
-                    if completion_key.is_ascii() {
-                        completion_haystack_ascii_set.insert(completion_key);
-                    } else {
-                        completion_haystack_non_ascii_set.insert(completion_key);
-                    }
-
-                    // Query map
-                    let query_key = normalize_for_query(key);
-                    query_map.entry(query_key.clone()).or_default().push(entry);
-                }
+    // Creates an engine from anonymous CSVs in the default layout. Each CSV becomes a source with priority 0 whose id is its position.
+    #[wasm_bindgen(constructor)]
+    pub fn new(base_csvs: Vec<String>) -> DictionaryEngine {
+        Self::from_sources(
+            base_csvs
+                .into_iter()
+                .enumerate()
+                .map(|(index, text)| DictionarySourceInput {
+                    id: index.to_string(),
+                    text,
+                    ..Default::default()
+                })
+                .collect(),
+            None,
+        )
+    }
 
-                dictionary_index += 1;
-            }
+    #[wasm_bindgen]
+    pub fn from_sources(
+        inputs: Vec<DictionarySourceInput>,
+        options: Option<DictionaryEngineOptions>,
+    ) -> DictionaryEngine {
+        let mut builder = DictionaryEngineBuilder::new(options);
+        for input in inputs {
+            builder.begin_source(input.id, Some(input.priority), Some(input.format));
+            builder.push_chunk(&input.text);
         }
+        builder.finish()
+    }
 
-        log_performance(
-            "CSV parsing and indexing",
-            parse_start.elapsed(),
-            Some(&format!(
-                "{} entries processed, {} lines skipped",
-                dictionary.len(),
-                skipped_lines
-            )),
-        );
+    /// Searches completion keys. Filters can be typed inline in the query: `c:name`, `category:name` or `#name`
+    /// for categories, `>n` or `>=n` for counts, and `!term` to skip keys containing a term.
+    #[wasm_bindgen]
+    pub fn fuzzy_search(
+        &mut self,
+        query: &str,
+        max_entries: Option<usize>,
+        force_try_non_ascii: Option<bool>,
+    ) -> Vec<CompletionResultEntry> {
+        self.fuzzy_search_with_options(
+            query,
+            Some(FuzzySearchOptions {
+                max_entries,
+                force_try_non_ascii,
+                ..Default::default()
+            }),
+        )
+    }
 
-        // Phase 2: Sorting entries in maps
-        let sort_start = Instant::now();
-        for indices in completion_map.values_mut() {
-            indices.sort_by_key(|v| ScoreableEntry {
-                index: v.index,
-                is_canonical: v.alias_index.is_none(),
-                count: dictionary[v.index].count as i64,
-            });
-        }
+    /// Like `fuzzy_search`, with additional filters and search modes. `max_entries` only counts entries that pass the filters.
+    #[wasm_bindgen]
+    pub fn fuzzy_search_with_options(
+        &mut self,
+        query: &str,
+        options: Option<FuzzySearchOptions>,
+    ) -> Vec<CompletionResultEntry> {
+        self.fuzzy_search_with_status(query, options).results
+    }
 
-        log_performance(
-            "Map sorting",
-            sort_start.elapsed(),
-            Some(&format!("{} completion entries", completion_map.len())),
-        );
+    /// Like `fuzzy_search_with_options`, also reporting whether the search was cut short by its deadline
+    /// or aborted by a newer generation.
+    #[wasm_bindgen]
+    pub fn fuzzy_search_with_status(
+        &mut self,
+        query: &str,
+        options: Option<FuzzySearchOptions>,
+    ) -> FuzzySearchResponse {
+        let start_time = Instant::now();
+        let options = options.unwrap_or_default();
 
-        // Phase 3: Haystack preparation and sorting
-        let haystack_start = Instant::now();
-        // Sort the haystacks
-        let score_map = completion_map
-            .iter()
-            .map(|(key, indices)| {
-                let count = indices
-                    .iter()
-                    .map(|entry| dictionary[entry.index].count as i64)
-                    .sum();
-                let is_canonical = indices.iter().any(|e| e.alias_index.is_none());
-                let index = indices.iter().min_by_key(|e| e.index).unwrap().index;
-
-                (
-                    key.clone(),
-                    ScoreableEntry {
-                        index,
-                        is_canonical,
-                        count,
-                    },
-                )
-            })
-            .collect::<HashMap<_, _>>();
+        if !self.begin_generation(options.generation) {
+            return FuzzySearchResponse {
+                aborted: true,
+                ..Default::default()
+            };
+        }
 
-        let mut completion_haystack_ascii: Vec<String> =
-            completion_haystack_ascii_set.into_iter().collect();
-        let mut completion_haystack_non_ascii: Vec<String> =
-            completion_haystack_non_ascii_set.into_iter().collect();
+        let (pattern, mut matches, interruption) = self.rank_matches(query, &options, start_time);
+        if interruption == Some(Interruption::Superseded) {
+            return FuzzySearchResponse {
+                aborted: true,
+                ..Default::default()
+            };
+        }
+        let truncated = interruption == Some(Interruption::Deadline);
+        sort_ranked_matches(&mut matches, options.max_entries);
 
-        completion_haystack_ascii.sort_by_key(|s| score_map.get(s).unwrap());
-        completion_haystack_non_ascii.sort_by_key(|s| score_map.get(s).unwrap());
+        // Phase 3: Result construction
+        let construction_start = Instant::now();
+        let mut results: Vec<CompletionResultEntry> = Vec::with_capacity(matches.len());
+        for ranked in matches {
+            let mut result = Self::create_completion_result_entry(&self.dictionary, &ranked);
+            if options.include_match_indices {
+                result.match_indices = Some(match_indices(
+                    &pattern,
+                    &result.term,
+                    &mut self.nucleo_matcher,
+                ));
+            }
+            results.push(result);
+        }
 
         log_performance(
-            "Haystack preparation",
-            haystack_start.elapsed(),
-            Some(&format!(
-                "ASCII: {}, Non-ASCII: {}",
-                completion_haystack_ascii.len(),
-                completion_haystack_non_ascii.len()
-            )),
+            "Result construction",
+            construction_start.elapsed(),
+            Some(&format!("results: {}", results.len())),
         );
 
-        // Create the Nucleo matcher
-        let nucleo_matcher = {
-            let mut config = Config::DEFAULT;
-            config.prefer_prefix = false;
-
-            Matcher::new(config)
-        };
-
         log_performance(
-            "DictionaryEngine initialization",
+            "fuzzy_search total",
             start_time.elapsed(),
-            Some(&format!("Total entries: {}", dictionary.len())),
+            Some(&format!(
+                "query: '{}', final_results: {}, truncated: {}",
+                query,
+                results.len(),
+                truncated
+            )),
         );
 
-        DictionaryEngine {
-            dictionary,
-            completion_haystack_ascii,
-            completion_haystack_non_ascii,
-            completion_map,
-            query_map,
-            nucleo_matcher,
+        FuzzySearchResponse {
+            results,
+            truncated,
+            aborted: false,
         }
     }
 
-    #[wasm_bindgen]
-    pub fn fuzzy_search(
+    // Matches the query against the haystacks, returning the pattern, every entry that passes
+    // the filters with its rank in matcher order, and why matching was cut short if it was.
+    // Superseded searches return no entries.
+    fn rank_matches(
         &mut self,
         query: &str,
-        max_entries: Option<usize>,
-        force_try_non_ascii: Option<bool>,
-    ) -> Vec<CompletionResultEntry> {
-        let start_time = Instant::now();
-
-        let completion_query = normalize_for_auto_completion(query);
-        let try_non_ascii = force_try_non_ascii.unwrap_or_else(|| !completion_query.is_ascii());
+        options: &FuzzySearchOptions,
+        start_time: Instant,
+    ) -> (Pattern, Vec<RankedMatch>, Option<Interruption>) {
+        let (query, inline_filters) = parse_query(query);
+        let mut options = inline_filters.apply(options);
+        // Excluded terms are compared against completion keys
+        for term in options.exclude_terms.iter_mut().flatten() {
+            *term = normalize_for_auto_completion(term);
+        }
+        let options = &options;
+        let completion_query = normalize_for_auto_completion(&query);
+        let try_non_ascii = options
+            .force_try_non_ascii
+            .unwrap_or_else(|| !completion_query.is_ascii());
 
         // Phase 1: Pattern parsing and matching
         let pattern_start = Instant::now();
         let pattern = Pattern::parse(&completion_query, CaseMatching::Smart, Normalization::Smart);
-        let nucleo_matches = if try_non_ascii {
-            pattern.match_list(
-                self.completion_haystack_ascii
-                    .iter()
-                    .chain(self.completion_haystack_non_ascii.iter()),
+        let budget = SearchBudget {
+            deadline: options
+                .deadline_ms
+                .and_then(|ms| deadline_after(start_time, ms)),
+            generation: options.generation,
+            signal: self.generation_signal.as_ref(),
+        };
+        let (nucleo_matches, interruption) = match options.mode {
+            SearchMode::Fuzzy => match_haystacks(
+                &pattern,
+                &completion_query,
+                try_non_ascii,
+                &self.completion_haystack_ascii,
+                &self.completion_haystack_non_ascii,
                 &mut self.nucleo_matcher,
-            )
-        } else {
-            pattern.match_list(&self.completion_haystack_ascii, &mut self.nucleo_matcher)
+                &mut self.search_cache,
+                &budget,
+            ),
+            mode => (
+                self.literal_matches(&completion_query, mode, try_non_ascii),
+                None,
+            ),
         };
+        if interruption == Some(Interruption::Superseded) {
+            return (pattern, Vec::new(), interruption);
+        }
+        // Matching can take the rest of the time a newer search was waiting for, so check once more
+        // before ranking. A deadline passing now truncates nothing, as every key was matched.
+        if interruption.is_none() && budget.check() == Some(Interruption::Superseded) {
+            return (pattern, Vec::new(), Some(Interruption::Superseded));
+        }
 
         log_performance(
             "Pattern matching",
             pattern_start.elapsed(),
             Some(&format!(
-                "query: '{}', matches: {}, try_non_ascii: {}",
+                "query: '{}', mode: {:?}, matches: {}, try_non_ascii: {}",
                 query,
+                options.mode,
                 nucleo_matches.len(),
                 try_non_ascii
             )),
         );
 
-        // Phase 2: Result construction
-        let construction_start = Instant::now();
+        // Phase 2: Ranking
+        let ranking_start = Instant::now();
+        let present = options
+            .present_tags
+            .as_deref()
+            .map(|tags| self.resolve_present_tags(tags))
+            .unwrap_or_default();
+        let mut matches = Vec::with_capacity(nucleo_matches.len());
+        for &(candidate, score) in &nucleo_matches {
+            let kind = MatchKind::of(candidate, &completion_query);
+            self.push_ranked_matches(
+                candidate,
+                score,
+                kind,
+                None,
+                options,
+                &present,
+                &mut matches,
+            );
+        }
 
-        // Pre-allocate with a reasonable capacity to avoid reallocations
-        let estimated_capacity = match max_entries {
-            Some(max) => max.min(nucleo_matches.len() * 2), // Estimate 2 entries per match on average
-            None => nucleo_matches.len() * 2,
-        };
-        let mut results: Vec<CompletionResultEntry> = Vec::with_capacity(estimated_capacity);
-
-        'outer: for (candidate, score) in nucleo_matches {
-            for &IndexEntry { index, alias_index } in
-                self.completion_map.get(candidate).iter().cloned().flatten()
-            {
-                results.push(Self::create_completion_result_entry(
-                    &self.dictionary,
-                    index,
-                    alias_index,
-                    score,
-                ));
-                if let Some(max) = max_entries {
-                    if results.len() >= max {
-                        break 'outer;
-                    }
-                }
+        // Fall back to typo corrections when the query finds too few tags
+        let threshold = options
+            .typo_fallback_threshold
+            .unwrap_or(DEFAULT_TYPO_FALLBACK_THRESHOLD);
+        if options.mode == SearchMode::Fuzzy && interruption.is_none() && matches.len() < threshold
+        {
+            let matched = nucleo_matches
+                .iter()
+                .map(|&(candidate, _)| candidate)
+                .collect::<HashSet<_>>();
+            for (candidate, distance) in self.typo_candidates(&completion_query, &matched) {
+                self.push_ranked_matches(
+                    candidate,
+                    0,
+                    MatchKind::Other,
+                    Some(distance),
+                    options,
+                    &present,
+                    &mut matches,
+                );
             }
         }
 
         log_performance(
-            "Result construction",
-            construction_start.elapsed(),
-            Some(&format!("results: {}", results.len())),
+            "Ranking",
+            ranking_start.elapsed(),
+            Some(&format!("entries: {}", matches.len())),
         );
 
-        log_performance(
-            "fuzzy_search total",
-            start_time.elapsed(),
-            Some(&format!(
-                "query: '{}', final_results: {}",
-                query,
-                results.len()
-            )),
-        );
+        (pattern, matches, interruption)
+    }
 
-        results
+    // Ranks the entries of a matched completion key that pass the filters. `typo_distance` is set
+    // for typo corrections.
+    #[allow(clippy::too_many_arguments)]
+    fn push_ranked_matches(
+        &self,
+        candidate: &str,
+        score: u32,
+        kind: MatchKind,
+        typo_distance: Option<usize>,
+        options: &FuzzySearchOptions,
+        present: &HashSet<usize>,
+        matches: &mut Vec<RankedMatch>,
+    ) {
+        if options
+            .exclude_terms
+            .iter()
+            .flatten()
+            .any(|term| !term.is_empty() && candidate.contains(term.as_str()))
+        {
+            return;
+        }
+
+        let weights = self.options.ranking;
+        for &IndexEntry { index, alias_index } in
+            self.completion_map.get(candidate).iter().cloned().flatten()
+        {
+            let entry = &self.dictionary[index];
+            if !options.accepts(entry) {
+                continue;
+            }
+            let is_present = present.contains(&index);
+            if is_present && options.present_tag_mode == PresentTagMode::Exclude {
+                continue;
+            }
+            let mut rank = weights.rank(
+                score,
+                entry.count,
+                entry.priority,
+                alias_index.is_none(),
+                kind,
+            );
+            if is_present {
+                rank -= weights.present_tag_penalty;
+            }
+            if let Some(distance) = typo_distance {
+                rank -= weights.typo_penalty * distance as f64;
+            }
+            matches.push(RankedMatch {
+                index,
+                alias_index,
+                score,
+                rank,
+                is_present,
+                is_typo_correction: typo_distance.is_some(),
+                order: matches.len(),
+            });
+        }
+    }
+
+    // The entries of a term exactly matching the word after normalization
+    fn lookup_word(&self, word: &str) -> Vec<QueryResultEntryValue> {
+        self.query_map
+            .get(&normalize_for_query(word))
+            .iter()
+            .copied()
+            .flatten()
+            .map(|&IndexEntry { index, alias_index }| {
+                let entry = &self.dictionary[index];
+                let (term, is_canonical) = match alias_index {
+                    Some(alias_index) => (entry.aliases[alias_index].clone(), false),
+                    None => (entry.key.clone(), true),
+                };
+                QueryResultEntryValue {
+                    term,
+                    canonical_key: entry.key.clone(),
+                    is_canonical,
+                    category: entry.category,
+                    count: entry.count,
+                    aliases: entry.aliases.clone(),
+                    source: entry.source.clone(),
+                }
+            })
+            .collect()
     }
 
     #[wasm_bindgen]
@@ -421,30 +958,7 @@ impl DictionaryEngine {
         let result = words
             .into_iter()
             .map(|word| {
-                let normalized_for_query = normalize_for_query(&word);
-                let indices = self.query_map.get(&normalized_for_query).cloned();
-                let entries = match indices {
-                    Some(indices) => indices
-                        .iter()
-                        .map(|&IndexEntry { index, alias_index }| {
-                            let entry = &self.dictionary[index];
-                            let (term, is_canonical) = match alias_index {
-                                Some(alias_index) => (entry.aliases[alias_index].clone(), false),
-                                None => (entry.key.clone(), true),
-                            };
-                            QueryResultEntryValue {
-                                term,
-                                canonical_key: entry.key.clone(),
-                                is_canonical,
-                                category: entry.category,
-                                count: entry.count,
-                                aliases: entry.aliases.clone(),
-                            }
-                        })
-                        .collect::<Vec<_>>(),
-                    None => vec![],
-                };
-
+                let entries = self.lookup_word(&word);
                 QueryResultEntry(word, entries)
             })
             .collect::<Vec<_>>();
@@ -471,7 +985,7 @@ mod tests {
     use super::*;
 
     // Test CSV data with N-M relations using realworld entries
-    fn create_test_csv_data() -> Vec<String> {
+    pub(super) fn create_test_csv_data() -> Vec<String> {
         vec![
             // Mix of custom test data and realworld entries showing N-M relations
             r#"1girl,0,5794009,"1girls,女の子,女性,少女,girl,おんなのこ,女子,소녀,女孩,姑娘,女,ガール,ガールズイラスト,animegirl"
@@ -649,6 +1163,199 @@ wind,6,18000,"windy,breeze,gust""#.to_string(),
         assert!(unlimited_results.len() >= results.len());
     }
 
+    #[test]
+    fn test_fuzzy_search_filters() {
+        let csv_data = create_test_csv_data();
+        let mut engine = DictionaryEngine::new(csv_data);
+
+        let results = engine.fuzzy_search_with_options(
+            "a",
+            Some(FuzzySearchOptions {
+                categories: Some(vec![6, 7]),
+                ..Default::default()
+            }),
+        );
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|r| r.category == 6 || r.category == 7));
+
+        let results = engine.fuzzy_search_with_options(
+            "a",
+            Some(FuzzySearchOptions {
+                exclude_categories: Some(vec![0]),
+                min_count: Some(30000),
+                ..Default::default()
+            }),
+        );
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|r| r.category != 0 && r.count >= 30000));
+
+        // max_entries only counts entries passing the filters
+        let unfiltered = engine.fuzzy_search("a", Some(3), None);
+        assert!(unfiltered.iter().all(|r| r.category == 0));
+        let results = engine.fuzzy_search_with_options(
+            "a",
+            Some(FuzzySearchOptions {
+                max_entries: Some(3),
+                categories: Some(vec![7]),
+                ..Default::default()
+            }),
+        );
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|r| r.category == 7));
+    }
+
+    #[test]
+    fn test_fuzzy_search_match_indices() {
+        let csv_data = create_test_csv_data();
+        let mut engine = DictionaryEngine::new(csv_data);
+        let options = FuzzySearchOptions {
+            include_match_indices: true,
+            ..Default::default()
+        };
+
+        let results = engine.fuzzy_search_with_options("lohai", Some(options.clone()));
+        let long_hair = results.iter().find(|r| r.term == "long_hair").unwrap();
+        assert_eq!(long_hair.match_indices, Some(vec![0, 1, 5, 6, 7]));
+
+        // Katakana terms are matched through their hiragana form
+        let results = engine.fuzzy_search_with_options("ろんぐへあ", Some(options.clone()));
+        let long_hair = results.iter().find(|r| r.term == "ロングヘア").unwrap();
+        assert_eq!(long_hair.match_indices, Some(vec![0, 1, 2, 3, 4]));
+        let results = engine.fuzzy_search_with_options("きんぱつ", Some(options.clone()));
+        assert!(results.iter().all(|r| r.match_indices.is_some()));
+
+        // Not included unless requested
+        let results = engine.fuzzy_search("lohai", Some(5), None);
+        assert!(results.iter().all(|r| r.match_indices.is_none()));
+    }
+
+    #[test]
+    fn test_fuzzy_search_present_tags() {
+        let csv_data = create_test_csv_data();
+        let mut engine = DictionaryEngine::new(csv_data);
+
+        let baseline = engine.fuzzy_search("hair", Some(3), None);
+        assert!(baseline.iter().any(|r| r.canonical_key == "long_hair"));
+
+        // Present tags are resolved through their aliases, and excluded before truncation
+        let results = engine.fuzzy_search_with_options(
+            "hair",
+            Some(FuzzySearchOptions {
+                max_entries: Some(3),
+                present_tags: Some(vec!["長髪".to_string(), "short hair".to_string()]),
+                ..Default::default()
+            }),
+        );
+        assert_eq!(results.len(), 3);
+        assert!(
+            results
+                .iter()
+                .all(|r| r.canonical_key != "long_hair" && r.canonical_key != "short_hair")
+        );
+
+        // Demoted tags stay in the results with a lower rank
+        let options = FuzzySearchOptions {
+            present_tags: Some(vec!["long_hair".to_string()]),
+            present_tag_mode: PresentTagMode::Demote,
+            ..Default::default()
+        };
+        let results = engine.fuzzy_search_with_options("long hair", Some(options));
+        let long_hair = results.iter().find(|r| r.term == "long_hair").unwrap();
+        assert!(long_hair.is_present);
+        let baseline = engine.fuzzy_search("long hair", None, None);
+        let baseline_rank = baseline
+            .iter()
+            .find(|r| r.term == "long_hair")
+            .unwrap()
+            .rank;
+        assert_eq!(long_hair.rank, baseline_rank - 20.0);
+        assert!(
+            results
+                .iter()
+                .filter(|r| r.canonical_key != "long_hair")
+                .all(|r| !r.is_present)
+        );
+    }
+
+    #[test]
+    fn test_romaji_secondary_keys() {
+        let options = DictionaryEngineOptions {
+            secondary_keys: SecondaryKeyOptions {
+                romaji: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let inputs = create_test_csv_data()
+            .into_iter()
+            .enumerate()
+            .map(|(index, text)| DictionarySourceInput {
+                id: index.to_string(),
+                text,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let mut engine = DictionaryEngine::from_sources(inputs.clone(), Some(options));
+
+        // Romaji keys point back to the kana alias
+        let results = engine.fuzzy_search("rongu", None, None);
+        let long_hair = results
+            .iter()
+            .find(|r| r.term == "ロングヘア")
+            .expect("kana alias found through romaji");
+        assert_eq!(long_hair.canonical_key, "long_hair");
+        assert!(!long_hair.is_canonical);
+
+        // Both Hepburn and Kunrei spellings are indexed
+        assert_eq!(
+            engine.completion_map["tsuinteru"],
+            engine.completion_map["tuinteru"]
+        );
+        assert!(engine.completion_map.contains_key("tsuinte"));
+
+        // Disabled by default
+        let mut engine = DictionaryEngine::from_sources(inputs, None);
+        let results = engine.fuzzy_search("rongu", None, None);
+        assert!(results.iter().all(|r| r.term != "ロングヘア"));
+    }
+
+    #[test]
+    fn test_pinyin_and_hangul_secondary_keys() {
+        let options = DictionaryEngineOptions {
+            secondary_keys: SecondaryKeyOptions {
+                pinyin: true,
+                hangul_initials: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let inputs = create_test_csv_data()
+            .into_iter()
+            .enumerate()
+            .map(|(index, text)| DictionarySourceInput {
+                id: index.to_string(),
+                text,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let mut engine = DictionaryEngine::from_sources(inputs, Some(options));
+
+        let results = engine.fuzzy_search("jinfa", None, None);
+        let blonde = results.iter().find(|r| r.term == "金发").unwrap();
+        assert_eq!(blonde.canonical_key, "blonde_hair");
+        assert!(
+            results
+                .iter()
+                .any(|r| r.term == "金发碧眼" && r.canonical_key == "blue_eyes")
+        );
+
+        let results = engine.fuzzy_search("ㄷㅂ", None, None);
+        let short_hair = results.iter().find(|r| r.term == "단발").unwrap();
+        assert_eq!(short_hair.canonical_key, "short_hair");
+        let results = engine.fuzzy_search("ㅅㄴ", Some(1), None);
+        assert_eq!(results[0].term, "소녀");
+    }
+
     #[test]
     fn test_query_words_exact_match() {
         let csv_data = create_test_csv_data();
@@ -772,26 +1479,39 @@ wind,6,18000,"windy,breeze,gust""#.to_string(),
         let entry1 = ScoreableEntry {
             index: 0,
             is_canonical: true,
+            priority: 0,
             count: 100,
         };
 
         let entry2 = ScoreableEntry {
             index: 1,
             is_canonical: false,
+            priority: 0,
             count: 200,
         };
 
         let entry3 = ScoreableEntry {
             index: 2,
             is_canonical: true,
+            priority: 0,
             count: 200,
         };
 
+        let entry4 = ScoreableEntry {
+            index: 3,
+            is_canonical: false,
+            priority: 1,
+            count: 1,
+        };
+
         // entry2 should come before entry1 (higher count)
         assert!(entry2 < entry1);
 
         // entry3 should come before entry2 (same count, but canonical)
         assert!(entry3 < entry2);
+
+        // entry4 should come before everything else (higher priority)
+        assert!(entry4 < entry3);
     }
 
     #[test]
@@ -1189,4 +1909,115 @@ wind,6,18000,"windy,breeze,gust""#.to_string(),
             assert_eq!(result.1[0].canonical_key, "1girl");
         }
     }
+
+    #[test]
+    fn test_source_provenance() {
+        let mut engine = DictionaryEngine::from_sources(
+            vec![
+                DictionarySourceInput {
+                    id: "danbooru".to_string(),
+                    text: "long_hair,0,4181922,\"ロングヘア,長髪\"".to_string(),
+                    ..Default::default()
+                },
+                DictionarySourceInput {
+                    id: "personal".to_string(),
+                    text: "my_style,1,10,\"長髪\"".to_string(),
+                    ..Default::default()
+                },
+            ],
+            None,
+        );
+
+        let results = engine.fuzzy_search("long_hair", Some(10), None);
+        let long_hair = results
+            .iter()
+            .find(|r| r.canonical_key == "long_hair")
+            .unwrap();
+        assert_eq!(long_hair.source, "danbooru");
+
+        let results = engine.query_words(vec!["長髪".to_string()]);
+        let sources = results[0]
+            .1
+            .iter()
+            .map(|e| (e.canonical_key.as_str(), e.source.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sources,
+            vec![("long_hair", "danbooru"), ("my_style", "personal")]
+        );
+    }
+
+    #[test]
+    fn test_default_source_ids() {
+        let engine = DictionaryEngine::new(vec![
+            "a,0,1,".to_string(),
+            "".to_string(),
+            "b,0,1,".to_string(),
+        ]);
+        assert_eq!(engine.dictionary[0].source, "0");
+        assert_eq!(engine.dictionary[1].source, "2");
+        assert!(engine.dictionary.iter().all(|e| e.priority == 0));
+    }
+
+    #[test]
+    fn test_priority_outranks_count() {
+        let mut engine = DictionaryEngine::from_sources(
+            vec![
+                DictionarySourceInput {
+                    id: "danbooru".to_string(),
+                    text: "aaa_hair,0,1000000,\nshared,0,1000000,".to_string(),
+                    ..Default::default()
+                },
+                DictionarySourceInput {
+                    id: "personal".to_string(),
+                    priority: 10,
+                    text: "bbb_hair,0,1,\nshared,0,1,".to_string(),
+                    ..Default::default()
+                },
+            ],
+            None,
+        );
+
+        // Equal match scores, so the haystack order decides
+        let results = engine.fuzzy_search("hair", Some(10), None);
+        assert_eq!(results[0].canonical_key, "bbb_hair");
+        assert_eq!(results[0].source, "personal");
+        assert_eq!(results[1].canonical_key, "aaa_hair");
+
+        // Entries sharing a completion key are ordered by priority as well
+        let results = engine.fuzzy_search("shared", Some(10), None);
+        assert_eq!(results[0].source, "personal");
+        assert_eq!(results[1].source, "danbooru");
+    }
+
+    #[test]
+    fn test_duplicate_source_ids_keep_last() {
+        let engine = DictionaryEngine::from_sources(
+            vec![
+                DictionarySourceInput {
+                    id: "user".to_string(),
+                    text: "first,0,1,".to_string(),
+                    ..Default::default()
+                },
+                DictionarySourceInput {
+                    id: "base".to_string(),
+                    text: "base,0,1,".to_string(),
+                    ..Default::default()
+                },
+                DictionarySourceInput {
+                    id: "user".to_string(),
+                    text: "second,0,1,".to_string(),
+                    ..Default::default()
+                },
+            ],
+            None,
+        );
+
+        let keys = engine
+            .dictionary
+            .iter()
+            .map(|e| e.key.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec!["base", "second"]);
+    }
 }
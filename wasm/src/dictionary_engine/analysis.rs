@@ -0,0 +1,106 @@
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use super::{DictionaryEngine, Instant, QueryResultEntryValue, log_performance};
+use crate::prompt::{Utf16Cursor, tag_tokens};
+
+// A tag of an analyzed prompt. Offsets are in UTF-16 code units, like CodeMirror positions.
+#[derive(Debug, Tsify, serde::Serialize)]
+#[tsify(into_wasm_abi)]
+pub struct PromptSpan {
+    // The token, trimmed
+    pub from: u32,
+    pub to: u32,
+    // The bare name within the token, before unescaping
+    pub name_from: u32,
+    pub name_to: u32,
+    pub name: String,
+    pub weight: f64,
+    // Whether the name resolved to dictionary entries
+    pub is_known: bool,
+    pub entries: Vec<QueryResultEntryValue>,
+}
+
+#[wasm_bindgen]
+impl DictionaryEngine {
+    /// Splits a prompt into comma and newline separated tags and resolves each of them like `query_fragments`.
+    /// Comments, chants, LoRAs and embeddings are skipped.
+    #[wasm_bindgen]
+    pub fn analyze_prompt(&self, text: &str) -> Vec<PromptSpan> {
+        let start_time = Instant::now();
+
+        let mut cursor = Utf16Cursor::new(text);
+        let spans = tag_tokens(text)
+            .into_iter()
+            .map(|token| {
+                let from = cursor.offset(token.range.start);
+                let name_from = cursor.offset(token.tag.name_range.start);
+                let name_to = cursor.offset(token.tag.name_range.end);
+                let to = cursor.offset(token.range.end);
+                let entries = self.lookup_word(&token.tag.name);
+                PromptSpan {
+                    from,
+                    to,
+                    name_from,
+                    name_to,
+                    name: token.tag.name,
+                    weight: token.tag.weight,
+                    is_known: !entries.is_empty(),
+                    entries,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        log_performance(
+            "analyze_prompt total",
+            start_time.elapsed(),
+            Some(&format!(
+                "spans: {}, unknown: {}",
+                spans.len(),
+                spans.iter().filter(|span| !span.is_known).count()
+            )),
+        );
+
+        spans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::create_test_csv_data;
+    use super::*;
+
+    fn utf16_slice(text: &str, from: u32, to: u32) -> String {
+        let units = text.encode_utf16().collect::<Vec<_>>();
+        String::from_utf16(&units[from as usize..to as usize]).unwrap()
+    }
+
+    #[test]
+    fn test_analyze_prompt() {
+        let engine = DictionaryEngine::new(create_test_csv_data());
+        let text = "😀 1girl, (長髪:1.2), // smile\n@chant, <lora:foo:0.8>, unknown_tag /* a, b */";
+        let spans = engine.analyze_prompt(text);
+
+        assert_eq!(
+            spans
+                .iter()
+                .map(|span| utf16_slice(text, span.from, span.to))
+                .collect::<Vec<_>>(),
+            vec!["😀 1girl", "(長髪:1.2)", "unknown_tag"]
+        );
+        assert!(!spans[0].is_known);
+
+        let long_hair = &spans[1];
+        assert!(long_hair.is_known);
+        assert_eq!(long_hair.weight, 1.2);
+        assert_eq!(long_hair.entries[0].canonical_key, "long_hair");
+        assert_eq!(
+            utf16_slice(text, long_hair.name_from, long_hair.name_to),
+            "長髪"
+        );
+
+        assert_eq!(spans[2].name, "unknown_tag");
+        assert!(!spans[2].is_known);
+        assert!(spans[2].entries.is_empty());
+    }
+}
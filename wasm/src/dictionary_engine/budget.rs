@@ -0,0 +1,222 @@
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use super::{CompletionResultEntry, DictionaryEngine, Duration, Instant};
+
+#[derive(Debug, Default, Tsify, serde::Serialize)]
+#[tsify(into_wasm_abi)]
+pub struct FuzzySearchResponse {
+    pub results: Vec<CompletionResultEntry>,
+    // Whether matching stopped at the deadline, so that less popular keys were not searched
+    pub truncated: bool,
+    // Whether the search was skipped or stopped because a newer generation was seen, in which case
+    // there are no results
+    pub aborted: bool,
+}
+
+// Reads the newest generation requested by the caller, which may change while a search runs
+pub(super) type GenerationSignal = Box<dyn Fn() -> u32>;
+
+// Why matching stopped before every key was matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Interruption {
+    // The deadline passed, and the keys matched so far are ranked
+    Deadline,
+    // A newer generation was requested, and the search is aborted
+    Superseded,
+}
+
+// Limits of a running search, checked between match chunks
+#[derive(Default)]
+pub(super) struct SearchBudget<'a> {
+    pub(super) deadline: Option<Instant>,
+    pub(super) generation: Option<u32>,
+    pub(super) signal: Option<&'a GenerationSignal>,
+}
+
+impl SearchBudget<'_> {
+    pub(super) fn check(&self) -> Option<Interruption> {
+        if let (Some(generation), Some(signal)) = (self.generation, self.signal)
+            && signal() > generation
+        {
+            Some(Interruption::Superseded)
+        } else if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            Some(Interruption::Deadline)
+        } else {
+            None
+        }
+    }
+}
+
+// The instant a budget of `ms` milliseconds from the start runs out. Budgets that are not finite
+// or go past the range of `Instant` have no deadline, and negative ones are already spent.
+pub(super) fn deadline_after(start: Instant, ms: f64) -> Option<Instant> {
+    if !ms.is_finite() {
+        return None;
+    }
+    let budget = Duration::try_from_secs_f64(ms.max(0.0) / 1000.0).ok()?;
+    start.checked_add(budget)
+}
+
+impl DictionaryEngine {
+    // Records the generation of a search, returning false if a newer one was already seen
+    pub(super) fn begin_generation(&mut self, generation: Option<u32>) -> bool {
+        let Some(generation) = generation else {
+            return true;
+        };
+        if let Some(signal) = &self.generation_signal {
+            self.search_generation = self.search_generation.max(signal());
+        }
+        if generation < self.search_generation {
+            return false;
+        }
+        self.search_generation = generation;
+        true
+    }
+}
+
+#[wasm_bindgen]
+impl DictionaryEngine {
+    /// Aborts searches whose generation is older than the given one, for callers that know a request
+    /// is stale before sending the next one.
+    #[wasm_bindgen]
+    pub fn cancel_searches_before(&mut self, generation: u32) {
+        self.search_generation = self.search_generation.max(generation);
+    }
+
+    /// Lets running searches see newer generations. `signal` is an `Int32Array` over a `SharedArrayBuffer`
+    /// whose first element the requesting thread sets to the newest generation with `Atomics.store`.
+    /// Searches with an older generation stop at the next chunk of keys and are aborted.
+    #[wasm_bindgen]
+    pub fn set_generation_signal(&mut self, signal: js_sys::Int32Array) {
+        self.generation_signal = Some(Box::new(move || {
+            js_sys::Atomics::load(&signal, 0).map_or(0, |generation| generation.max(0) as u32)
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::super::tests::create_test_csv_data;
+    use super::super::{FuzzySearchOptions, SearchMode};
+    use super::*;
+
+    fn search(engine: &mut DictionaryEngine, generation: u32) -> FuzzySearchResponse {
+        engine.fuzzy_search_with_status(
+            "hair",
+            Some(FuzzySearchOptions {
+                generation: Some(generation),
+                deadline_ms: Some(1000.0),
+                ..Default::default()
+            }),
+        )
+    }
+
+    #[test]
+    fn test_deadline_after() {
+        let start = Instant::now();
+        assert_eq!(
+            deadline_after(start, 1500.0),
+            Some(start + Duration::from_millis(1500))
+        );
+        assert_eq!(deadline_after(start, -10.0), Some(start));
+        for ms in [f64::INFINITY, f64::NEG_INFINITY, f64::NAN, 1e300, f64::MAX] {
+            assert_eq!(deadline_after(start, ms), None, "{ms}");
+        }
+
+        // Searches with such budgets run to completion
+        let mut engine = DictionaryEngine::new(create_test_csv_data());
+        for ms in [f64::INFINITY, 1e300] {
+            let response = engine.fuzzy_search_with_status(
+                "hair",
+                Some(FuzzySearchOptions {
+                    deadline_ms: Some(ms),
+                    ..Default::default()
+                }),
+            );
+            assert!(!response.truncated);
+            assert!(!response.results.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_generations() {
+        let mut engine = DictionaryEngine::new(create_test_csv_data());
+
+        let response = search(&mut engine, 2);
+        assert!(!response.aborted);
+        assert!(!response.truncated);
+        assert!(!response.results.is_empty());
+
+        // Older requests are aborted, repeated and newer ones run
+        assert!(search(&mut engine, 1).aborted);
+        assert!(!search(&mut engine, 2).aborted);
+        assert!(!search(&mut engine, 3).aborted);
+
+        engine.cancel_searches_before(5);
+        let response = search(&mut engine, 4);
+        assert!(response.aborted);
+        assert!(response.results.is_empty());
+        assert!(!search(&mut engine, 5).aborted);
+
+        // Searches without a generation always run
+        assert!(!engine.fuzzy_search("hair", None, None).is_empty());
+    }
+
+    #[test]
+    fn test_superseded_while_running() {
+        let csv = (0..10_000)
+            .map(|i| format!("hair_{i},0,{i},\n"))
+            .collect::<String>();
+        let mut engine = DictionaryEngine::new(vec![csv]);
+
+        // The caller requests generation 2 right after the search for generation 1 starts
+        let checks = Rc::new(Cell::new(0));
+        let signal_checks = checks.clone();
+        engine.generation_signal = Some(Box::new(move || {
+            signal_checks.set(signal_checks.get() + 1);
+            if signal_checks.get() == 1 { 1 } else { 2 }
+        }));
+
+        let response = search(&mut engine, 1);
+        assert!(response.aborted);
+        assert!(response.results.is_empty());
+        assert!(checks.get() > 1);
+
+        assert!(search(&mut engine, 1).aborted);
+        let response = search(&mut engine, 2);
+        assert!(!response.aborted);
+        assert!(!response.results.is_empty());
+    }
+
+    #[test]
+    fn test_superseded_before_ranking() {
+        let mut engine = DictionaryEngine::new(create_test_csv_data());
+
+        // Literal matching has no chunks, so the generation is only seen again before ranking
+        let checks = Rc::new(Cell::new(0));
+        let signal_checks = checks.clone();
+        engine.generation_signal = Some(Box::new(move || {
+            signal_checks.set(signal_checks.get() + 1);
+            if signal_checks.get() == 1 { 1 } else { 2 }
+        }));
+
+        let response = engine.fuzzy_search_with_status(
+            "hair",
+            Some(FuzzySearchOptions {
+                mode: SearchMode::Substring,
+                generation: Some(1),
+                ..Default::default()
+            }),
+        );
+        assert!(response.aborted);
+        assert!(response.results.is_empty());
+        assert_eq!(checks.get(), 2);
+    }
+}
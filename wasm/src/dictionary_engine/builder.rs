@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use super::formats::SourceParser;
+use super::{
+    DictionaryEngine, DictionaryEngineOptions, DictionaryEntry, DictionaryFormat, DictionarySource,
+    IndexEntry, Instant, index_entry, log_performance, log_warning, merge_duplicates,
+    sort_haystack, sort_index_entries,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Tsify, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BuildPhase {
+    // Receiving and parsing source text
+    Parse,
+    // Sorting the entries of each completion key
+    Sort,
+    // Building the sorted completion haystacks
+    Haystack,
+    Done,
+}
+
+#[derive(Debug, Clone, Tsify, serde::Serialize)]
+#[tsify(into_wasm_abi)]
+pub struct BuildProgress {
+    pub phase: BuildPhase,
+    pub sources_processed: usize,
+    // Rows parsed so far across all sources, including skipped ones
+    pub rows_processed: usize,
+    pub entries_loaded: usize,
+}
+
+struct CurrentSource {
+    id: String,
+    parser: SourceParser,
+}
+
+/// Builds a `DictionaryEngine` from text pushed in chunks, reporting progress along the way.
+#[wasm_bindgen]
+pub struct DictionaryEngineBuilder {
+    options: DictionaryEngineOptions,
+    dictionary: Vec<DictionaryEntry>,
+    sources: Vec<DictionarySource>,
+    current: Option<CurrentSource>,
+    // Rows parsed by the sources that have been ended
+    rows_processed: usize,
+    phase: BuildPhase,
+    on_progress: Option<js_sys::Function>,
+    start_time: Instant,
+}
+
+#[wasm_bindgen]
+impl DictionaryEngineBuilder {
+    #[wasm_bindgen(constructor)]
+    pub fn new(options: Option<DictionaryEngineOptions>) -> DictionaryEngineBuilder {
+        DictionaryEngineBuilder {
+            options: options.unwrap_or_default(),
+            dictionary: Vec::new(),
+            sources: Vec::new(),
+            current: None,
+            rows_processed: 0,
+            phase: BuildPhase::Parse,
+            on_progress: None,
+            start_time: Instant::now(),
+        }
+    }
+
+    /// Registers a function called with a `BuildProgress` after every chunk and phase change.
+    #[wasm_bindgen]
+    pub fn set_progress_callback(&mut self, callback: js_sys::Function) {
+        self.on_progress = Some(callback);
+    }
+
+    /// Ends the current source and starts a new one. A source with the same id is replaced.
+    #[wasm_bindgen]
+    pub fn begin_source(
+        &mut self,
+        id: String,
+        priority: Option<i32>,
+        format: Option<DictionaryFormat>,
+    ) {
+        self.end_source();
+
+        if let Some(position) = self.sources.iter().position(|s| s.id == id) {
+            log_warning(&format!("Duplicate source id '{id}' replaced"));
+            let start = self.sources[..position]
+                .iter()
+                .map(|s| s.len)
+                .sum::<usize>();
+            let source = self.sources.remove(position);
+            self.dictionary.drain(start..start + source.len);
+            self.rows_processed -= source.total_lines;
+        }
+
+        self.current = Some(CurrentSource {
+            parser: SourceParser::new(
+                format.unwrap_or_default(),
+                id.clone(),
+                priority.unwrap_or(0),
+            ),
+            id,
+        });
+    }
+
+    /// Appends text to the current source. Rows may be split across chunks.
+    /// Without a current source, one is started with its position as id, like `DictionaryEngine::new` does.
+    #[wasm_bindgen]
+    pub fn push_chunk(&mut self, chunk: &str) {
+        if self.current.is_none() {
+            self.begin_source(self.sources.len().to_string(), None, None);
+        }
+        if let Some(current) = &mut self.current {
+            current.parser.push(chunk);
+        }
+        self.report_progress();
+    }
+
+    /// Parses whatever is left of the current source. Called automatically by `begin_source` and `finish`.
+    #[wasm_bindgen]
+    pub fn end_source(&mut self) {
+        let Some(current) = self.current.take() else {
+            return;
+        };
+        let (source, entries) = current.parser.finish().into_source(current.id);
+        self.rows_processed += source.total_lines;
+        self.sources.push(source);
+        self.dictionary.extend(entries);
+    }
+
+    #[wasm_bindgen]
+    pub fn progress(&self) -> BuildProgress {
+        let current = self.current.as_ref();
+        BuildProgress {
+            phase: self.phase,
+            sources_processed: self.sources.len(),
+            rows_processed: self.rows_processed + current.map_or(0, |c| c.parser.total_lines()),
+            entries_loaded: self.dictionary.len() + current.map_or(0, |c| c.parser.entries()),
+        }
+    }
+
+    /// Indexes and sorts everything that was pushed, producing the engine.
+    #[wasm_bindgen]
+    pub fn finish(mut self) -> DictionaryEngine {
+        self.end_source();
+
+        // Phase 1: Merging duplicates and indexing
+        let index_start = Instant::now();
+        let duplicates = merge_duplicates(&mut self.dictionary, self.options.merge_policy);
+
+        let mut completion_map: HashMap<String, Vec<IndexEntry>> = HashMap::new();
+        let mut query_map: HashMap<String, Vec<IndexEntry>> = HashMap::new();
+        for (index, entry) in self.dictionary.iter().enumerate() {
+            if !entry.shadowed {
+                index_entry(
+                    entry,
+                    index,
+                    &mut completion_map,
+                    &mut query_map,
+                    &self.options.secondary_keys,
+                    None,
+                );
+            }
+        }
+
+        log_performance(
+            "CSV parsing and indexing",
+            self.start_time.elapsed(),
+            Some(&format!(
+                "{} entries processed, {} lines skipped, {} duplicate keys merged ({:?}), indexing took {:.2}ms",
+                self.dictionary.len(),
+                self.sources
+                    .iter()
+                    .map(|s| s.skipped_lines.len())
+                    .sum::<usize>(),
+                duplicates.len(),
+                self.options.merge_policy,
+                index_start.elapsed().as_millis()
+            )),
+        );
+
+        // Phase 2: Sorting entries in maps
+        self.set_phase(BuildPhase::Sort);
+        let sort_start = Instant::now();
+        for indices in completion_map.values_mut() {
+            sort_index_entries(&self.dictionary, indices);
+        }
+
+        log_performance(
+            "Map sorting",
+            sort_start.elapsed(),
+            Some(&format!("{} completion entries", completion_map.len())),
+        );
+
+        // Phase 3: Haystack preparation and sorting
+        self.set_phase(BuildPhase::Haystack);
+        let haystack_start = Instant::now();
+        let (mut completion_haystack_ascii, mut completion_haystack_non_ascii): (
+            Vec<String>,
+            Vec<String>,
+        ) = completion_map
+            .keys()
+            .cloned()
+            .partition(|key| key.is_ascii());
+
+        sort_haystack(
+            &mut completion_haystack_ascii,
+            &self.dictionary,
+            &completion_map,
+        );
+        sort_haystack(
+            &mut completion_haystack_non_ascii,
+            &self.dictionary,
+            &completion_map,
+        );
+        let mut completion_key_index = completion_map.keys().cloned().collect::<Vec<_>>();
+        completion_key_index.sort_unstable();
+
+        log_performance(
+            "Haystack preparation",
+            haystack_start.elapsed(),
+            Some(&format!(
+                "ASCII: {}, Non-ASCII: {}",
+                completion_haystack_ascii.len(),
+                completion_haystack_non_ascii.len()
+            )),
+        );
+
+        self.set_phase(BuildPhase::Done);
+        log_performance(
+            "DictionaryEngine initialization",
+            self.start_time.elapsed(),
+            Some(&format!("Total entries: {}", self.dictionary.len())),
+        );
+
+        DictionaryEngine {
+            dictionary: self.dictionary,
+            sources: self.sources,
+            duplicates,
+            options: self.options,
+            completion_haystack_ascii,
+            completion_haystack_non_ascii,
+            completion_key_index,
+            completion_map,
+            query_map,
+            nucleo_matcher: DictionaryEngine::create_nucleo_matcher(),
+            search_cache: None,
+            search_generation: 0,
+            generation_signal: None,
+        }
+    }
+}
+
+impl DictionaryEngineBuilder {
+    fn set_phase(&mut self, phase: BuildPhase) {
+        self.phase = phase;
+        self.report_progress();
+    }
+
+    fn report_progress(&self) {
+        let Some(callback) = &self.on_progress else {
+            return;
+        };
+        let progress = match serde_wasm_bindgen::to_value(&self.progress()) {
+            Ok(progress) => progress,
+            Err(err) => {
+                log_warning(&format!("Failed to serialize build progress: {err}"));
+                return;
+            }
+        };
+        if let Err(err) = callback.call1(&JsValue::NULL, &progress) {
+            log_warning(&format!("Progress callback failed: {err:?}"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::create_test_csv_data;
+    use super::super::{DictionarySourceInput, DuplicateMergePolicy};
+    use super::*;
+
+    fn assert_same_engine(actual: &DictionaryEngine, expected: &DictionaryEngine) {
+        assert_eq!(actual.dictionary, expected.dictionary);
+        assert_eq!(actual.completion_map, expected.completion_map);
+        assert_eq!(actual.query_map, expected.query_map);
+        assert_eq!(
+            actual.completion_haystack_ascii,
+            expected.completion_haystack_ascii
+        );
+        assert_eq!(
+            actual.completion_haystack_non_ascii,
+            expected.completion_haystack_non_ascii
+        );
+    }
+
+    #[test]
+    fn test_chunked_build_matches_new() {
+        let csvs = create_test_csv_data();
+        let expected = DictionaryEngine::new(csvs.clone());
+
+        for chunk_size in [1, 7, 64, 4096] {
+            let mut builder = DictionaryEngineBuilder::new(None);
+            for csv in &csvs {
+                let chars = csv.chars().collect::<Vec<_>>();
+                for chunk in chars.chunks(chunk_size) {
+                    builder.push_chunk(&chunk.iter().collect::<String>());
+                }
+                builder.end_source();
+            }
+            assert_same_engine(&builder.finish(), &expected);
+        }
+    }
+
+    #[test]
+    fn test_progress() {
+        let mut builder = DictionaryEngineBuilder::new(None);
+        builder.begin_source("base".to_string(), None, None);
+        builder.push_chunk("1girl,0,100,girl\nsolo,0,");
+        let progress = builder.progress();
+        assert_eq!(progress.phase, BuildPhase::Parse);
+        assert_eq!(progress.sources_processed, 0);
+        assert_eq!(progress.rows_processed, 1);
+        assert_eq!(progress.entries_loaded, 1);
+
+        builder.push_chunk("50,\nbroken,0,x,\n");
+        builder.begin_source("user".to_string(), Some(5), None);
+        builder.push_chunk("cat,7,10,neko");
+        let progress = builder.progress();
+        assert_eq!(progress.sources_processed, 1);
+        assert_eq!(progress.rows_processed, 3);
+        assert_eq!(progress.entries_loaded, 2);
+
+        let engine = builder.finish();
+        let report = engine.load_report();
+        assert_eq!(report.total_entries, 3);
+        assert_eq!(report.skipped_lines.len(), 1);
+        assert_eq!(engine.dictionary[2].priority, 5);
+    }
+
+    #[test]
+    fn test_replaced_source_and_options() {
+        let mut builder = DictionaryEngineBuilder::new(Some(DictionaryEngineOptions {
+            merge_policy: DuplicateMergePolicy::FirstWins,
+            ..Default::default()
+        }));
+        builder.begin_source("a".to_string(), None, None);
+        builder.push_chunk("tag,0,1,old\n");
+        builder.begin_source("b".to_string(), None, None);
+        builder.push_chunk("tag,0,2,b\n");
+        builder.begin_source("a".to_string(), None, None);
+        builder.push_chunk("tag,0,3,new\n");
+        assert_eq!(builder.progress().rows_processed, 2);
+
+        let expected = DictionaryEngine::from_sources(
+            vec![
+                DictionarySourceInput {
+                    id: "b".to_string(),
+                    text: "tag,0,2,b\n".to_string(),
+                    ..Default::default()
+                },
+                DictionarySourceInput {
+                    id: "a".to_string(),
+                    text: "tag,0,3,new\n".to_string(),
+                    ..Default::default()
+                },
+            ],
+            Some(DictionaryEngineOptions {
+                merge_policy: DuplicateMergePolicy::FirstWins,
+                ..Default::default()
+            }),
+        );
+        let engine = builder.finish();
+        assert_same_engine(&engine, &expected);
+        assert_eq!(engine.dictionary[0].source, "b");
+    }
+}
@@ -0,0 +1,340 @@
+use std::ops::Range;
+
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use super::{DictionaryEngine, Instant, log_performance};
+use crate::normalize::normalize_for_query;
+use crate::prompt::{Utf16Cursor, escape_parentheses, tag_tokens};
+
+// How tag names written into a prompt are spelled, like the `normalize` compile option
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Tsify, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TagNameStyle {
+    // As in the dictionary
+    #[default]
+    None,
+    Underscore,
+    Whitespace,
+}
+
+impl TagNameStyle {
+    pub(super) fn apply(self, name: &str) -> String {
+        match self {
+            TagNameStyle::None => name.to_string(),
+            TagNameStyle::Underscore => name.replace(' ', "_"),
+            TagNameStyle::Whitespace => name.replace('_', " "),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Tsify, serde::Deserialize)]
+#[tsify(from_wasm_abi)]
+#[serde(default)]
+pub struct CanonicalizeOptions {
+    #[tsify(optional)]
+    pub name_style: TagNameStyle,
+    // Escape parentheses in written names, like the `parentheses` escape target. Names replacing
+    // ones written with parentheses keep the escaping of the original instead.
+    #[tsify(optional)]
+    pub escape_parentheses: bool,
+}
+
+// A name rewritten in a prompt. Offsets are UTF-16 code units into the original prompt.
+#[derive(Debug, Tsify, serde::Serialize)]
+#[tsify(into_wasm_abi)]
+pub struct PromptReplacement {
+    pub from: u32,
+    pub to: u32,
+    pub original: String,
+    pub replacement: String,
+}
+
+// A tag name resolving to several canonical tags, left as is
+#[derive(Debug, Tsify, serde::Serialize)]
+#[tsify(into_wasm_abi)]
+pub struct AmbiguousPromptTag {
+    pub from: u32,
+    pub to: u32,
+    pub name: String,
+    pub canonical_keys: Vec<String>,
+}
+
+#[derive(Debug, Tsify, serde::Serialize)]
+#[tsify(into_wasm_abi)]
+pub struct CanonicalizedPrompt {
+    pub text: String,
+    pub replacements: Vec<PromptReplacement>,
+    pub ambiguous: Vec<AmbiguousPromptTag>,
+}
+
+// Rebuilds a prompt with some ranges replaced, copying everything else. Ranges must be given in order.
+pub(super) struct PromptRewriter<'a> {
+    text: &'a str,
+    output: String,
+    copied: usize,
+    cursor: Utf16Cursor<'a>,
+    replacements: Vec<PromptReplacement>,
+}
+
+impl<'a> PromptRewriter<'a> {
+    pub(super) fn new(text: &'a str) -> Self {
+        PromptRewriter {
+            text,
+            output: String::with_capacity(text.len()),
+            copied: 0,
+            cursor: Utf16Cursor::new(text),
+            replacements: Vec::new(),
+        }
+    }
+
+    // UTF-16 offsets of a byte range of the prompt
+    pub(super) fn offsets(&mut self, range: &Range<usize>) -> (u32, u32) {
+        (
+            self.cursor.offset(range.start),
+            self.cursor.offset(range.end),
+        )
+    }
+
+    // Replaces a byte range of the prompt, unless it already reads the same
+    pub(super) fn replace(&mut self, range: Range<usize>, replacement: String) {
+        if self.text[range.clone()] == replacement {
+            return;
+        }
+        let (from, to) = self.offsets(&range);
+        self.output.push_str(&self.text[self.copied..range.start]);
+        self.output.push_str(&replacement);
+        self.copied = range.end;
+        self.replacements.push(PromptReplacement {
+            from,
+            to,
+            original: self.text[range].to_string(),
+            replacement,
+        });
+    }
+
+    pub(super) fn finish(mut self) -> (String, Vec<PromptReplacement>) {
+        self.output.push_str(&self.text[self.copied..]);
+        (self.output, self.replacements)
+    }
+}
+
+impl DictionaryEngine {
+    // The distinct canonical keys a tag name resolves to, in dictionary order
+    pub(super) fn canonical_keys(&self, name: &str) -> Vec<&str> {
+        let mut keys: Vec<&str> = Vec::new();
+        let mut normalized_keys = Vec::new();
+        for entry in self
+            .query_map
+            .get(&normalize_for_query(name))
+            .iter()
+            .copied()
+            .flatten()
+        {
+            let key = &self.dictionary[entry.index].key;
+            let normalized = normalize_for_query(key);
+            if !normalized_keys.contains(&normalized) {
+                normalized_keys.push(normalized);
+                keys.push(key);
+            }
+        }
+        keys
+    }
+}
+
+#[wasm_bindgen]
+impl DictionaryEngine {
+    /// Rewrites every tag resolving to a single canonical tag with its canonical key. Weights, order, comments
+    /// and separators are kept. Tags resolving to several canonical tags are reported instead of rewritten.
+    #[wasm_bindgen]
+    pub fn canonicalize_prompt(
+        &self,
+        text: &str,
+        options: Option<CanonicalizeOptions>,
+    ) -> CanonicalizedPrompt {
+        let start_time = Instant::now();
+        let options = options.unwrap_or_default();
+
+        let mut rewriter = PromptRewriter::new(text);
+        let mut ambiguous = Vec::new();
+
+        for token in tag_tokens(text) {
+            let range = token.tag.name_range;
+            let keys = self.canonical_keys(&token.tag.name);
+            match keys.as_slice() {
+                [] => {}
+                [key] => {
+                    let name = options.name_style.apply(key);
+                    // Differences in escaping alone are not rewritten
+                    if name == token.tag.name {
+                        continue;
+                    }
+                    let original = &text[range.clone()];
+                    let escaped = if original.contains(['(', ')']) {
+                        original.contains("\\(") || original.contains("\\)")
+                    } else {
+                        options.escape_parentheses
+                    };
+                    let replacement = if escaped {
+                        escape_parentheses(&name)
+                    } else {
+                        name
+                    };
+                    rewriter.replace(range, replacement);
+                }
+                _ => {
+                    let (from, to) = rewriter.offsets(&range);
+                    ambiguous.push(AmbiguousPromptTag {
+                        from,
+                        to,
+                        name: token.tag.name,
+                        canonical_keys: keys.iter().map(|key| key.to_string()).collect(),
+                    });
+                }
+            }
+        }
+        let (output, replacements) = rewriter.finish();
+
+        log_performance(
+            "canonicalize_prompt total",
+            start_time.elapsed(),
+            Some(&format!(
+                "replacements: {}, ambiguous: {}",
+                replacements.len(),
+                ambiguous.len()
+            )),
+        );
+
+        CanonicalizedPrompt {
+            text: output,
+            replacements,
+            ambiguous,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::DictionarySourceInput;
+    use super::super::tests::create_test_csv_data;
+    use super::*;
+
+    #[test]
+    fn test_canonicalize_prompt() {
+        let engine = DictionaryEngine::new(create_test_csv_data());
+        let text =
+            "金髪, (smiling:1.2) // 長髪, smiling\n[[ロングヘア]], -girl, long_hair, unknown_tag";
+        let result = engine.canonicalize_prompt(text, None);
+
+        assert_eq!(
+            result.text,
+            "blonde_hair, (smile:1.2) // 長髪, smiling\n[[long_hair]], -1girl, long_hair, unknown_tag"
+        );
+        assert_eq!(
+            result
+                .replacements
+                .iter()
+                .map(|r| (r.original.as_str(), r.replacement.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("金髪", "blonde_hair"),
+                ("smiling", "smile"),
+                ("ロングヘア", "long_hair"),
+                ("girl", "1girl"),
+            ]
+        );
+        // Offsets are UTF-16 code units into the original prompt
+        let smiling = &result.replacements[1];
+        assert_eq!((smiling.from, smiling.to), (5, 12));
+        assert!(result.ambiguous.is_empty());
+    }
+
+    #[test]
+    fn test_canonicalize_options() {
+        let engine = DictionaryEngine::from_sources(
+            vec![DictionarySourceInput {
+                id: "artists".to_string(),
+                text: "artist_(style),1,100,\"style artist\"\n".to_string(),
+                ..Default::default()
+            }],
+            None,
+        );
+        let result = engine.canonicalize_prompt(
+            "(style artist:0.8)",
+            Some(CanonicalizeOptions {
+                name_style: TagNameStyle::Whitespace,
+                escape_parentheses: true,
+            }),
+        );
+        assert_eq!(result.text, "(artist \\(style\\):0.8)");
+
+        // Already canonical tags are left alone
+        let result = engine.canonicalize_prompt(
+            "artist_\\(style\\)",
+            Some(CanonicalizeOptions {
+                escape_parentheses: true,
+                ..Default::default()
+            }),
+        );
+        assert!(result.replacements.is_empty());
+    }
+
+    #[test]
+    fn test_canonicalize_keeps_escaping() {
+        let engine = DictionaryEngine::from_sources(
+            vec![DictionarySourceInput {
+                id: "artists".to_string(),
+                text: "artist_(style),1,100,\"artist (style),style artist\"\ntag,0,10,alias\n"
+                    .to_string(),
+                ..Default::default()
+            }],
+            None,
+        );
+
+        // Escaped canonical tags are left alone, so that their parentheses are not read as emphasis
+        let text = "artist_\\(style\\), (tag:1.2), (alias:1.2)";
+        let result = engine.canonicalize_prompt(text, None);
+        assert_eq!(result.text, "artist_\\(style\\), (tag:1.2), (tag:1.2)");
+        assert_eq!(result.replacements.len(), 1);
+
+        // Replacements of escaped names are escaped
+        let result = engine.canonicalize_prompt("(artist \\(style\\):0.8)", None);
+        assert_eq!(result.text, "(artist_\\(style\\):0.8)");
+
+        // Names written with unescaped parentheses stay unescaped
+        let options = CanonicalizeOptions {
+            escape_parentheses: true,
+            ..Default::default()
+        };
+        let result = engine.canonicalize_prompt("artist_(style)", Some(options.clone()));
+        assert!(result.replacements.is_empty());
+        let result = engine.canonicalize_prompt("artist (style)", Some(options));
+        assert_eq!(result.text, "artist_(style)");
+    }
+
+    #[test]
+    fn test_ambiguous_tags_are_reported() {
+        let engine = DictionaryEngine::from_sources(
+            vec![
+                DictionarySourceInput {
+                    id: "a".to_string(),
+                    text: "cat_ears,0,100,\"neko,nekomimi\"\ncat,0,50,neko\n".to_string(),
+                    ..Default::default()
+                },
+                DictionarySourceInput {
+                    id: "b".to_string(),
+                    text: "cat_ears,0,100,nekomimi\n".to_string(),
+                    ..Default::default()
+                },
+            ],
+            None,
+        );
+        let result = engine.canonicalize_prompt("neko, nekomimi", None);
+
+        assert_eq!(result.text, "neko, cat_ears");
+        assert_eq!(result.ambiguous.len(), 1);
+        assert_eq!(result.ambiguous[0].name, "neko");
+        assert_eq!(result.ambiguous[0].canonical_keys, vec!["cat_ears", "cat"]);
+        assert_eq!((result.ambiguous[0].from, result.ambiguous[0].to), (0, 4));
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::{HashMap, HashSet};
+
+use tsify::Tsify;
+
+use super::DictionaryEntry;
+use crate::normalize::normalize_for_query;
+
+// How rows sharing the same key (compared with `normalize_for_query`) are combined
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Tsify, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum DuplicateMergePolicy {
+    // Keep every row as a separate entry
+    #[default]
+    KeepAll,
+    // Keep the row from the earliest source
+    FirstWins,
+    // Keep the row from the latest source
+    LastWins,
+    // Keep the earliest row, with the counts of all rows added up
+    SumCounts,
+    // Keep the earliest row, with the aliases of all rows combined
+    UnionAliases,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(super) struct DuplicateGroup {
+    // Dictionary indices of every row sharing the key, in source order.
+    // The first member holds the merged entry, the others are shadowed.
+    pub(super) members: Vec<usize>,
+    // The original row of the first member, which is overwritten by the merged entry
+    pub(super) primary: DictionaryEntry,
+}
+
+pub(super) fn merge_entries(
+    policy: DuplicateMergePolicy,
+    rows: &[&DictionaryEntry],
+) -> DictionaryEntry {
+    let first = rows[0];
+    let mut merged = match policy {
+        DuplicateMergePolicy::KeepAll | DuplicateMergePolicy::FirstWins => first.clone(),
+        DuplicateMergePolicy::LastWins => rows[rows.len() - 1].clone(),
+        DuplicateMergePolicy::SumCounts => DictionaryEntry {
+            count: rows
+                .iter()
+                .fold(0i32, |sum, row| sum.saturating_add(row.count)),
+            ..first.clone()
+        },
+        DuplicateMergePolicy::UnionAliases => {
+            let mut seen = HashSet::new();
+            DictionaryEntry {
+                aliases: rows
+                    .iter()
+                    .flat_map(|row| row.aliases.iter())
+                    .filter(|alias| seen.insert(alias.as_str()))
+                    .cloned()
+                    .collect(),
+                ..first.clone()
+            }
+        }
+    };
+    merged.shadowed = false;
+    merged
+}
+
+impl DuplicateGroup {
+    // Merges the member rows, storing the result in the first member and shadowing the rest.
+    // All members must currently hold their original rows.
+    pub(super) fn create(
+        members: Vec<usize>,
+        dictionary: &mut [DictionaryEntry],
+        policy: DuplicateMergePolicy,
+    ) -> DuplicateGroup {
+        let rows = members.iter().map(|&m| &dictionary[m]).collect::<Vec<_>>();
+        let merged = merge_entries(policy, &rows);
+
+        let primary_index = members[0];
+        let primary = DictionaryEntry {
+            shadowed: false,
+            ..dictionary[primary_index].clone()
+        };
+        dictionary[primary_index] = merged;
+        for &member in &members[1..] {
+            dictionary[member].shadowed = true;
+        }
+
+        DuplicateGroup { members, primary }
+    }
+
+    // Puts the original row back into the first member, undoing the merge
+    pub(super) fn restore(&self, dictionary: &mut [DictionaryEntry]) {
+        dictionary[self.members[0]] = self.primary.clone();
+    }
+}
+
+// Merges rows sharing a key in a freshly parsed dictionary
+pub(super) fn merge_duplicates(
+    dictionary: &mut [DictionaryEntry],
+    policy: DuplicateMergePolicy,
+) -> HashMap<String, DuplicateGroup> {
+    if policy == DuplicateMergePolicy::KeepAll {
+        return HashMap::new();
+    }
+
+    let mut members_by_key: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, entry) in dictionary.iter().enumerate() {
+        members_by_key
+            .entry(normalize_for_query(&entry.key))
+            .or_default()
+            .push(index);
+    }
+
+    members_by_key
+        .into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .map(|(key, members)| (key, DuplicateGroup::create(members, dictionary, policy)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{DictionaryEngine, DictionaryEngineOptions, DictionarySourceInput};
+    use super::*;
+
+    fn build(policy: DuplicateMergePolicy) -> DictionaryEngine {
+        DictionaryEngine::from_sources(
+            vec![
+                DictionarySourceInput {
+                    id: "danbooru".to_string(),
+                    text: "long_hair,0,4000,\"長髪,ロングヘア\"\nsmile,0,3000,笑顔".to_string(),
+                    ..Default::default()
+                },
+                DictionarySourceInput {
+                    id: "personal".to_string(),
+                    text: "long hair,1,10,\"長髪,長い髪\"\ncat,7,5,neko".to_string(),
+                    ..Default::default()
+                },
+            ],
+            Some(DictionaryEngineOptions {
+                merge_policy: policy,
+                ..Default::default()
+            }),
+        )
+    }
+
+    fn query_long_hair(engine: &DictionaryEngine) -> Vec<(String, i32, String, Vec<String>)> {
+        engine.query_words(vec!["long_hair".to_string()])[0]
+            .1
+            .iter()
+            .map(|e| {
+                (
+                    e.canonical_key.clone(),
+                    e.count,
+                    e.source.clone(),
+                    e.aliases.clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_keep_all() {
+        let engine = build(DuplicateMergePolicy::KeepAll);
+        assert!(engine.duplicates.is_empty());
+        assert_eq!(query_long_hair(&engine).len(), 2);
+    }
+
+    #[test]
+    fn test_first_wins() {
+        let mut engine = build(DuplicateMergePolicy::FirstWins);
+        assert_eq!(
+            query_long_hair(&engine),
+            vec![(
+                "long_hair".to_string(),
+                4000,
+                "danbooru".to_string(),
+                vec!["長髪".to_string(), "ロングヘア".to_string()]
+            )]
+        );
+
+        // Aliases only present in the dropped row are not indexed
+        assert!(
+            engine.query_words(vec!["長い髪".to_string()])[0]
+                .1
+                .is_empty()
+        );
+        let results = engine.fuzzy_search("long hair", None, None);
+        assert_eq!(
+            results
+                .iter()
+                .filter(|r| r.canonical_key.starts_with("long"))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_last_wins() {
+        let engine = build(DuplicateMergePolicy::LastWins);
+        assert_eq!(
+            query_long_hair(&engine),
+            vec![(
+                "long hair".to_string(),
+                10,
+                "personal".to_string(),
+                vec!["長髪".to_string(), "長い髪".to_string()]
+            )]
+        );
+        assert!(
+            engine.query_words(vec!["ロングヘア".to_string()])[0]
+                .1
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_sum_counts() {
+        let engine = build(DuplicateMergePolicy::SumCounts);
+        assert_eq!(
+            query_long_hair(&engine),
+            vec![(
+                "long_hair".to_string(),
+                4010,
+                "danbooru".to_string(),
+                vec!["長髪".to_string(), "ロングヘア".to_string()]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_union_aliases() {
+        let engine = build(DuplicateMergePolicy::UnionAliases);
+        assert_eq!(
+            query_long_hair(&engine),
+            vec![(
+                "long_hair".to_string(),
+                4000,
+                "danbooru".to_string(),
+                vec![
+                    "長髪".to_string(),
+                    "ロングヘア".to_string(),
+                    "長い髪".to_string()
+                ]
+            )]
+        );
+
+        // The shared alias resolves to the single merged entry
+        let results = engine.query_words(vec!["長髪".to_string()]);
+        assert_eq!(results[0].1.len(), 1);
+        let results = engine.query_words(vec!["長い髪".to_string()]);
+        assert_eq!(results[0].1[0].canonical_key, "long_hair");
+    }
+
+    #[test]
+    fn test_duplicates_within_one_source() {
+        let engine = DictionaryEngine::from_sources(
+            vec![DictionarySourceInput {
+                id: "base".to_string(),
+                text: "tag,0,1,a\nother,0,1,\ntag,0,2,b\ntag,0,3,c".to_string(),
+                ..Default::default()
+            }],
+            Some(DictionaryEngineOptions {
+                merge_policy: DuplicateMergePolicy::SumCounts,
+                ..Default::default()
+            }),
+        );
+
+        let group = &engine.duplicates["tag"];
+        assert_eq!(group.members, vec![0, 2, 3]);
+        assert_eq!(group.primary.count, 1);
+        assert_eq!(engine.dictionary[0].count, 6);
+        assert!(engine.dictionary[2].shadowed);
+        assert!(engine.dictionary[3].shadowed);
+
+        let results = engine.query_words(vec!["tag".to_string()]);
+        assert_eq!(results[0].1.len(), 1);
+        assert_eq!(results[0].1[0].count, 6);
+    }
+
+    #[test]
+    fn test_merge_entries_saturates_counts() {
+        let row = DictionaryEntry {
+            key: "tag".to_string(),
+            category: 0,
+            count: i32::MAX,
+            aliases: vec![],
+            source: "base".to_string(),
+            priority: 0,
+            shadowed: false,
+        };
+        let merged = merge_entries(DuplicateMergePolicy::SumCounts, &[&row, &row]);
+        assert_eq!(merged.count, i32::MAX);
+    }
+}
@@ -0,0 +1,804 @@
+use tsify::Tsify;
+
+use super::{DictionaryEntry, DictionarySource, SkipReason, SkippedLine, log_warning};
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Tsify, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum DictionaryFormatKind {
+    #[default]
+    Csv,
+    Tsv,
+    JsonLines,
+}
+
+// Describes the layout of a dictionary source.
+// The default is a headerless CSV with the columns `key,category,count,aliases`.
+#[derive(Debug, Clone, Default, PartialEq, Tsify, serde::Serialize, serde::Deserialize)]
+#[tsify(from_wasm_abi)]
+#[serde(default)]
+pub struct DictionaryFormat {
+    #[tsify(optional)]
+    pub kind: DictionaryFormatKind,
+    // Whether the first row names the columns (CSV and TSV only)
+    #[tsify(optional)]
+    pub has_headers: bool,
+    // Column names for sources without a header row (CSV and TSV only)
+    #[tsify(optional)]
+    pub columns: Option<Vec<String>>,
+}
+
+const DEFAULT_COLUMNS: [&str; 4] = ["key", "category", "count", "aliases"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Key,
+    Category,
+    Count,
+    // Comma separated aliases. Translation columns are indexed as aliases too.
+    Aliases,
+}
+
+impl Field {
+    fn from_column_name(name: &str) -> Option<Field> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "key" | "name" | "tag" => Some(Field::Key),
+            "category" | "type" => Some(Field::Category),
+            "count" | "post_count" | "postcount" => Some(Field::Count),
+            "aliases" | "alias" | "translation" | "translations" => Some(Field::Aliases),
+            _ => None,
+        }
+    }
+}
+
+// Column positions of each field. Unknown columns are ignored.
+#[derive(Debug)]
+struct ColumnMap {
+    // Number of columns, including unknown ones
+    len: usize,
+    key: usize,
+    category: Option<usize>,
+    count: Option<usize>,
+    aliases: Vec<usize>,
+}
+
+impl ColumnMap {
+    fn from_names<'a>(names: impl IntoIterator<Item = &'a str>) -> Result<ColumnMap, String> {
+        let mut key = None;
+        let mut category = None;
+        let mut count = None;
+        let mut aliases = Vec::new();
+        let mut len = 0;
+
+        for (index, name) in names.into_iter().enumerate() {
+            len += 1;
+            match Field::from_column_name(name) {
+                Some(Field::Key) => key = key.or(Some(index)),
+                Some(Field::Category) => category = category.or(Some(index)),
+                Some(Field::Count) => count = count.or(Some(index)),
+                Some(Field::Aliases) => aliases.push(index),
+                None => {}
+            }
+        }
+
+        Ok(ColumnMap {
+            len,
+            key: key.ok_or_else(|| "No key column found".to_string())?,
+            category,
+            count,
+            aliases,
+        })
+    }
+
+    // Rows must contain the key, category and count columns. Alias columns are optional.
+    fn required_len(&self) -> usize {
+        [Some(self.key), self.category, self.count]
+            .into_iter()
+            .flatten()
+            .max()
+            .map_or(0, |i| i + 1)
+    }
+}
+
+// The result of parsing one source, before it is added to the dictionary
+pub(super) struct ParsedSource {
+    pub(super) entries: Vec<DictionaryEntry>,
+    pub(super) total_lines: usize,
+    pub(super) skipped_lines: Vec<SkippedLine>,
+}
+
+impl ParsedSource {
+    fn new() -> ParsedSource {
+        ParsedSource {
+            entries: Vec::new(),
+            total_lines: 0,
+            skipped_lines: Vec::new(),
+        }
+    }
+
+    pub(super) fn into_source(self, id: String) -> (DictionarySource, Vec<DictionaryEntry>) {
+        let source = DictionarySource {
+            id,
+            len: self.entries.len(),
+            total_lines: self.total_lines,
+            skipped_lines: self.skipped_lines,
+        };
+        (source, self.entries)
+    }
+
+    fn skip(&mut self, source: &str, line: u64, raw: &str, reason: SkipReason, message: String) {
+        log_warning(&format!(
+            "Skipped line {line} of source '{source}' ({reason:?}): {message}"
+        ));
+        self.skipped_lines.push(SkippedLine {
+            source: source.to_string(),
+            line,
+            raw: raw.to_string(),
+            reason,
+            message,
+        });
+    }
+}
+
+// Returns the line starting at the given byte offset, for diagnostics
+fn raw_line_at(text: &str, byte: u64) -> &str {
+    text.get(byte as usize..)
+        .and_then(|rest| rest.lines().next())
+        .unwrap_or_default()
+}
+
+fn parse_number(value: Option<&str>) -> Result<i32, String> {
+    match value {
+        Some(value) => value
+            .trim()
+            .parse()
+            .map_err(|err| format!("Invalid number '{value}': {err}")),
+        None => Ok(0),
+    }
+}
+
+fn create_entry<'a>(
+    key: &str,
+    category: i32,
+    count: i32,
+    aliases: impl Iterator<Item = &'a str>,
+    source: &str,
+    priority: i32,
+) -> Option<DictionaryEntry> {
+    let key = key.trim();
+    if key.is_empty() {
+        return None;
+    }
+
+    Some(DictionaryEntry {
+        key: key.to_string(),
+        category,
+        count,
+        aliases: aliases
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect(),
+        source: source.to_string(),
+        priority,
+        shadowed: false,
+    })
+}
+
+// Reads a JSON value as a list of aliases. Strings are treated as comma separated lists.
+fn json_aliases(value: Option<&serde_json::Value>) -> Vec<&str> {
+    match value {
+        Some(serde_json::Value::String(s)) => s.split(',').collect(),
+        Some(serde_json::Value::Array(values)) => values
+            .iter()
+            .filter_map(serde_json::Value::as_str)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// Reads the first of the given fields as an `i32`. Missing and null fields are read as 0.
+fn json_number(
+    object: &serde_json::Map<String, serde_json::Value>,
+    names: &[&str],
+) -> Result<i32, String> {
+    match names.iter().find_map(|name| object.get(*name)) {
+        None | Some(serde_json::Value::Null) => Ok(0),
+        Some(value) => value
+            .as_i64()
+            .and_then(|n| i32::try_from(n).ok())
+            .ok_or_else(|| format!("Invalid number '{value}'")),
+    }
+}
+
+pub(super) fn parse_source(
+    text: &str,
+    format: &DictionaryFormat,
+    source: &str,
+    priority: i32,
+) -> ParsedSource {
+    let mut parser = SourceParser::new(format.clone(), source.to_string(), priority);
+    parser.push(text);
+    parser.finish()
+}
+
+// Finds the lines of increasing byte offsets within a segment, counting line breaks as it goes
+struct LineCursor {
+    byte: usize,
+    // The line at `byte`, counted from the start of the source
+    line: u64,
+}
+
+impl LineCursor {
+    // Returns the line number and start of the row at the given byte offset, which must not be
+    // before the previous one. The offset reported by the CSV reader may point at preceding line
+    // breaks, which are skipped since rows never start with one.
+    fn locate(&mut self, segment: &str, byte: u64) -> (u64, u64) {
+        let bytes = segment.as_bytes();
+        let mut start = (byte as usize).clamp(self.byte, bytes.len());
+        while matches!(bytes.get(start), Some(b'\r' | b'\n')) {
+            start += 1;
+        }
+        self.line += bytes[self.byte..start]
+            .iter()
+            .filter(|&&b| b == b'\n')
+            .count() as u64;
+        self.byte = start;
+        (self.line, start as u64)
+    }
+}
+
+// Where the row boundary scanner is within a CSV row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScanState {
+    FieldStart,
+    Unquoted,
+    Quoted,
+    // A quote inside a quoted field, which either ends the field or escapes another quote
+    QuoteInQuoted,
+}
+
+// Parses a source from chunks of text that may split rows anywhere.
+// Complete rows are parsed as soon as they arrive, the rest is buffered.
+pub(super) struct SourceParser {
+    format: DictionaryFormat,
+    source: String,
+    priority: i32,
+    // Resolved before the first row, from the header row if the format has one
+    columns: Option<ColumnMap>,
+    // Set when no key column was found, after which every row is ignored
+    failed: bool,
+    // Whether any non-whitespace text has been seen. Completely empty sources are skipped.
+    started: bool,
+    pending: String,
+    // How far `pending` has been scanned for row boundaries, and the scanner state there
+    scanned: usize,
+    state: ScanState,
+    // Number of lines before `pending`
+    line_offset: u64,
+    parsed: ParsedSource,
+}
+
+impl SourceParser {
+    pub(super) fn new(format: DictionaryFormat, source: String, priority: i32) -> SourceParser {
+        SourceParser {
+            format,
+            source,
+            priority,
+            columns: None,
+            failed: false,
+            started: false,
+            pending: String::new(),
+            scanned: 0,
+            state: ScanState::FieldStart,
+            line_offset: 0,
+            parsed: ParsedSource::new(),
+        }
+    }
+
+    // Number of rows parsed so far, including skipped ones
+    pub(super) fn total_lines(&self) -> usize {
+        self.parsed.total_lines
+    }
+
+    // Number of entries parsed so far
+    pub(super) fn entries(&self) -> usize {
+        self.parsed.entries.len()
+    }
+
+    pub(super) fn push(&mut self, chunk: &str) {
+        self.pending.push_str(chunk);
+        if !self.started {
+            if self.pending.trim().is_empty() {
+                return;
+            }
+            self.started = true;
+        }
+
+        let boundary = self.scan_row_boundary();
+        if let Some(boundary) = boundary {
+            let rest = self.pending.split_off(boundary);
+            let complete = std::mem::replace(&mut self.pending, rest);
+            self.scanned -= boundary;
+            self.parse_segment(&complete);
+        }
+    }
+
+    pub(super) fn finish(mut self) -> ParsedSource {
+        if self.started && !self.pending.is_empty() {
+            let rest = std::mem::take(&mut self.pending);
+            self.parse_segment(&rest);
+        }
+        self.parsed
+    }
+
+    // Scans the unscanned part of `pending`, returning the end of the last complete row
+    fn scan_row_boundary(&mut self) -> Option<usize> {
+        let quoting = self.format.kind == DictionaryFormatKind::Csv;
+        let delimiter = self.delimiter();
+        let mut boundary = None;
+
+        for (offset, byte) in self.pending.as_bytes()[self.scanned..].iter().enumerate() {
+            if *byte == b'\n' && self.state != ScanState::Quoted {
+                boundary = Some(self.scanned + offset + 1);
+                self.state = ScanState::FieldStart;
+                continue;
+            }
+            if !quoting {
+                continue;
+            }
+            self.state = match (self.state, *byte) {
+                (ScanState::FieldStart, b'"') => ScanState::Quoted,
+                (ScanState::Quoted, b'"') => ScanState::QuoteInQuoted,
+                (ScanState::Quoted, _) => ScanState::Quoted,
+                (ScanState::QuoteInQuoted, b'"') => ScanState::Quoted,
+                (_, b'\r') => ScanState::FieldStart,
+                (_, byte) if byte == delimiter => ScanState::FieldStart,
+                (ScanState::FieldStart | ScanState::QuoteInQuoted, _) => ScanState::Unquoted,
+                (ScanState::Unquoted, _) => ScanState::Unquoted,
+            };
+        }
+        self.scanned = self.pending.len();
+
+        boundary
+    }
+
+    fn delimiter(&self) -> u8 {
+        match self.format.kind {
+            DictionaryFormatKind::Tsv => b'\t',
+            _ => b',',
+        }
+    }
+
+    fn parse_segment(&mut self, segment: &str) {
+        match self.format.kind {
+            DictionaryFormatKind::Csv | DictionaryFormatKind::Tsv => self.parse_delimited(segment),
+            DictionaryFormatKind::JsonLines => self.parse_json_lines(segment),
+        }
+        self.line_offset += segment.matches('\n').count() as u64;
+    }
+
+    fn skip(&mut self, line: u64, raw: &str, reason: SkipReason, message: String) {
+        self.parsed.skip(&self.source, line, raw, reason, message);
+    }
+
+    // `raw` is only evaluated if the entry is skipped
+    fn push_entry<'a, 'r>(
+        &mut self,
+        line: u64,
+        raw: impl FnOnce() -> &'r str,
+        key: &str,
+        category: i32,
+        count: i32,
+        aliases: impl Iterator<Item = &'a str>,
+    ) {
+        match create_entry(key, category, count, aliases, &self.source, self.priority) {
+            Some(entry) => self.parsed.entries.push(entry),
+            None => {
+                let message = "Key is empty".to_string();
+                self.skip(line, raw(), SkipReason::EmptyKey, message);
+            }
+        }
+    }
+
+    fn parse_delimited(&mut self, segment: &str) {
+        if self.failed {
+            return;
+        }
+
+        // Row lengths are checked against the columns rather than the first row of the chunk,
+        // so that chunking cannot change the result
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .delimiter(self.delimiter())
+            .quoting(self.format.kind == DictionaryFormatKind::Csv)
+            .from_reader(segment.as_bytes());
+
+        if self.columns.is_none() && !self.format.has_headers {
+            let columns = match &self.format.columns {
+                Some(columns) => ColumnMap::from_names(columns.iter().map(String::as_str)),
+                None => ColumnMap::from_names(DEFAULT_COLUMNS),
+            };
+            match columns {
+                Ok(columns) => self.columns = Some(columns),
+                Err(message) => {
+                    self.failed = true;
+                    let raw = raw_line_at(segment, 0);
+                    self.skip(1, raw, SkipReason::ParseError, message);
+                    return;
+                }
+            }
+        }
+
+        let mut lines = LineCursor {
+            byte: 0,
+            line: self.line_offset + 1,
+        };
+        let mut record = csv::StringRecord::new();
+        loop {
+            match reader.read_record(&mut record) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(err) => {
+                    self.parsed.total_lines += 1;
+                    let byte = err.position().map_or(0, |p| p.byte());
+                    let (line, start) = lines.locate(segment, byte);
+                    let raw = raw_line_at(segment, start);
+                    self.skip(line, raw, SkipReason::ParseError, err.to_string());
+                    continue; // Skip this line and continue processing
+                }
+            }
+
+            let byte = record.position().map_or(0, |p| p.byte());
+            let (line, start) = lines.locate(segment, byte);
+            let raw = || raw_line_at(segment, start);
+
+            let Some(columns) = &self.columns else {
+                // The first row names the columns and is not counted as a line
+                match ColumnMap::from_names(record.iter()) {
+                    Ok(columns) => self.columns = Some(columns),
+                    Err(message) => {
+                        self.failed = true;
+                        self.skip(line, raw(), SkipReason::ParseError, message);
+                        return;
+                    }
+                }
+                continue;
+            };
+            self.parsed.total_lines += 1;
+
+            if record.len() < columns.required_len() {
+                let message = format!(
+                    "Expected at least {} fields, found {}",
+                    columns.required_len(),
+                    record.len()
+                );
+                self.skip(line, raw(), SkipReason::ParseError, message);
+                continue;
+            }
+            if record.len() > columns.len {
+                let message = format!(
+                    "Expected at most {} fields, found {}",
+                    columns.len,
+                    record.len()
+                );
+                self.skip(line, raw(), SkipReason::ParseError, message);
+                continue;
+            }
+            let category = match parse_number(columns.category.and_then(|i| record.get(i))) {
+                Ok(category) => category,
+                Err(message) => {
+                    self.skip(line, raw(), SkipReason::ParseError, message);
+                    continue;
+                }
+            };
+            let count = match parse_number(columns.count.and_then(|i| record.get(i))) {
+                Ok(count) => count,
+                Err(message) => {
+                    self.skip(line, raw(), SkipReason::BadCount, message);
+                    continue;
+                }
+            };
+
+            let key = record.get(columns.key).unwrap_or_default();
+            let aliases = columns
+                .aliases
+                .iter()
+                .filter_map(|&i| record.get(i))
+                .flat_map(|s| s.split(','))
+                .collect::<Vec<_>>();
+            self.push_entry(line, raw, key, category, count, aliases.into_iter());
+        }
+    }
+
+    fn parse_json_lines(&mut self, segment: &str) {
+        for (line_index, raw) in segment.lines().enumerate() {
+            if raw.trim().is_empty() {
+                continue;
+            }
+            self.parsed.total_lines += 1;
+            let line = self.line_offset + line_index as u64 + 1;
+
+            let object = match serde_json::from_str::<serde_json::Value>(raw) {
+                Ok(serde_json::Value::Object(object)) => object,
+                Ok(_) => {
+                    let message = "Expected a JSON object".to_string();
+                    self.skip(line, raw, SkipReason::ParseError, message);
+                    continue;
+                }
+                Err(err) => {
+                    self.skip(line, raw, SkipReason::ParseError, err.to_string());
+                    continue;
+                }
+            };
+
+            let key = ["key", "name", "tag"]
+                .iter()
+                .find_map(|name| object.get(*name))
+                .and_then(serde_json::Value::as_str)
+                .unwrap_or_default();
+            let category = match json_number(&object, &["category", "type"]) {
+                Ok(category) => category,
+                Err(message) => {
+                    self.skip(line, raw, SkipReason::ParseError, message);
+                    continue;
+                }
+            };
+            let count = match json_number(&object, &["count", "post_count", "postCount"]) {
+                Ok(count) => count,
+                Err(message) => {
+                    self.skip(line, raw, SkipReason::BadCount, message);
+                    continue;
+                }
+            };
+            let aliases = ["aliases", "alias", "translation", "translations"]
+                .iter()
+                .flat_map(|name| json_aliases(object.get(*name)));
+
+            self.push_entry(line, || raw, key, category, count, aliases);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str, format: DictionaryFormat) -> ParsedSource {
+        parse_source(text, &format, "test", 0)
+    }
+
+    fn summarize(parsed: &ParsedSource) -> Vec<(&str, i32, i32, Vec<&str>)> {
+        parsed
+            .entries
+            .iter()
+            .map(|e| {
+                (
+                    e.key.as_str(),
+                    e.category,
+                    e.count,
+                    e.aliases.iter().map(String::as_str).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_default_csv_layout() {
+        let parsed = parse(
+            "long_hair,0,4181922,\"ロングヘア,長髪\"\nsolo,0,100,",
+            DictionaryFormat::default(),
+        );
+        assert_eq!(
+            summarize(&parsed),
+            vec![
+                ("long_hair", 0, 4181922, vec!["ロングヘア", "長髪"]),
+                ("solo", 0, 100, vec![]),
+            ]
+        );
+        assert_eq!(parsed.total_lines, 2);
+    }
+
+    #[test]
+    fn test_csv_with_headers() {
+        let parsed = parse(
+            "count,name,extra,type,translation,aliases\n\
+             4181922,long_hair,ignored,0,長髪,\"ロングヘア,long\"\n\
+             25000,cat,,7,猫,",
+            DictionaryFormat {
+                has_headers: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            summarize(&parsed),
+            vec![
+                ("long_hair", 0, 4181922, vec!["長髪", "ロングヘア", "long"]),
+                ("cat", 7, 25000, vec!["猫"]),
+            ]
+        );
+        // The header row is not counted as a line
+        assert_eq!(parsed.total_lines, 2);
+        assert_eq!(parsed.skipped_lines.len(), 0);
+    }
+
+    #[test]
+    fn test_csv_with_headers_without_key_column() {
+        let parsed = parse(
+            "foo,bar\n1,2",
+            DictionaryFormat {
+                has_headers: true,
+                ..Default::default()
+            },
+        );
+        assert!(parsed.entries.is_empty());
+        assert_eq!(parsed.skipped_lines.len(), 1);
+        assert_eq!(parsed.skipped_lines[0].raw, "foo,bar");
+        assert_eq!(parsed.skipped_lines[0].reason, SkipReason::ParseError);
+    }
+
+    #[test]
+    fn test_extra_columns_are_skipped() {
+        let parsed = parse(
+            "long_hair,0,100,長髪\nsolo,0,5,,extra\nshort,0,1",
+            DictionaryFormat::default(),
+        );
+        assert_eq!(
+            summarize(&parsed),
+            vec![("long_hair", 0, 100, vec!["長髪"]), ("short", 0, 1, vec![])]
+        );
+        assert_eq!(parsed.skipped_lines.len(), 1);
+        assert_eq!(parsed.skipped_lines[0].line, 2);
+        assert_eq!(parsed.skipped_lines[0].raw, "solo,0,5,,extra");
+        assert_eq!(parsed.skipped_lines[0].reason, SkipReason::ParseError);
+        assert_eq!(
+            parsed.skipped_lines[0].message,
+            "Expected at most 4 fields, found 5"
+        );
+    }
+
+    #[test]
+    fn test_headerless_custom_columns() {
+        // Translation files only carry a tag and its translations
+        let parsed = parse(
+            "long_hair,長髪\nblonde_hair,\"金髪,ブロンド\"",
+            DictionaryFormat {
+                columns: Some(vec!["tag".to_string(), "translation".to_string()]),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            summarize(&parsed),
+            vec![
+                ("long_hair", 0, 0, vec!["長髪"]),
+                ("blonde_hair", 0, 0, vec!["金髪", "ブロンド"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tsv() {
+        let parsed = parse(
+            "key\tcount\taliases\nlong_hair\t100\tlong,\"quoted\"\n\t5\tx\nbad\tmany\t",
+            DictionaryFormat {
+                kind: DictionaryFormatKind::Tsv,
+                has_headers: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            summarize(&parsed),
+            vec![("long_hair", 0, 100, vec!["long", "\"quoted\""])]
+        );
+        let reasons = parsed
+            .skipped_lines
+            .iter()
+            .map(|s| (s.line, s.reason))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            reasons,
+            vec![(3, SkipReason::EmptyKey), (4, SkipReason::BadCount)]
+        );
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let parsed = parse(
+            r#"{"key": "long_hair", "category": 0, "count": 4181922, "aliases": ["ロングヘア", "長髪"]}
+
+{"name": "cat", "type": 7, "post_count": 25000, "aliases": "kitten,neko", "translation": "猫"}
+{"key": "", "count": 1}
+{"key": "broken", "count": "many"}
+["not", "an", "object"]
+{"key": "unterminated"
+{"tag": "minimal"}"#,
+            DictionaryFormat {
+                kind: DictionaryFormatKind::JsonLines,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            summarize(&parsed),
+            vec![
+                ("long_hair", 0, 4181922, vec!["ロングヘア", "長髪"]),
+                ("cat", 7, 25000, vec!["kitten", "neko", "猫"]),
+                ("minimal", 0, 0, vec![]),
+            ]
+        );
+        assert_eq!(parsed.total_lines, 7);
+
+        let skipped = parsed
+            .skipped_lines
+            .iter()
+            .map(|s| (s.line, s.reason))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            skipped,
+            vec![
+                (4, SkipReason::EmptyKey),
+                (5, SkipReason::BadCount),
+                (6, SkipReason::ParseError),
+                (7, SkipReason::ParseError),
+            ]
+        );
+        assert_eq!(
+            parsed.skipped_lines[1].raw,
+            r#"{"key": "broken", "count": "many"}"#
+        );
+    }
+
+    #[test]
+    fn test_chunked_parsing_matches_whole_text() {
+        let cases = [
+            (
+                "long_hair,0,100,\"ロングヘア,\"\"長髪\"\"\nwith newline\"\r\nbad,0,x,\n\n,0,1,\r\n\r\nshort,0\nextra,0,1,a,b\nsolo,0,5,",
+                DictionaryFormat::default(),
+            ),
+            (
+                "key\tcount\nlong_hair\t100\n\"quoted\t5\nbad\tmany",
+                DictionaryFormat {
+                    kind: DictionaryFormatKind::Tsv,
+                    has_headers: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                "{\"key\": \"a\", \"count\": 1}\n\n{\"key\": \"\"}\n{\"tag\": \"猫\"}",
+                DictionaryFormat {
+                    kind: DictionaryFormatKind::JsonLines,
+                    ..Default::default()
+                },
+            ),
+        ];
+
+        for (text, format) in cases {
+            let expected = parse(text, format.clone());
+            let boundaries = text
+                .char_indices()
+                .map(|(i, _)| i)
+                .chain([text.len()])
+                .collect::<Vec<_>>();
+
+            // Split the text into two chunks at every position, and into single characters
+            for &split in &boundaries {
+                let mut parser = SourceParser::new(format.clone(), "test".to_string(), 0);
+                parser.push(&text[..split]);
+                parser.push(&text[split..]);
+                let actual = parser.finish();
+                assert_eq!(summarize(&actual), summarize(&expected), "Split at {split}");
+                assert_eq!(actual.skipped_lines, expected.skipped_lines);
+                assert_eq!(actual.total_lines, expected.total_lines);
+            }
+
+            let mut parser = SourceParser::new(format.clone(), "test".to_string(), 0);
+            for window in boundaries.windows(2) {
+                parser.push(&text[window[0]..window[1]]);
+            }
+            let actual = parser.finish();
+            assert_eq!(summarize(&actual), summarize(&expected));
+            assert_eq!(actual.skipped_lines, expected.skipped_lines);
+        }
+    }
+}
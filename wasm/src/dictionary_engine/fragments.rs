@@ -0,0 +1,111 @@
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use super::{DictionaryEngine, Instant, QueryResultEntryValue, log_performance};
+use crate::prompt::parse_weighted_tag;
+
+#[derive(Debug, Tsify, serde::Serialize)]
+#[tsify(into_wasm_abi)]
+pub struct FragmentQueryResult {
+    pub fragment: String,
+    // The bare tag name the fragment was resolved by
+    pub name: String,
+    // Weight from emphasis and weight syntax, 1.0 for a bare name and 0.0 for `-tag`
+    pub weight: f64,
+    pub entries: Vec<QueryResultEntryValue>,
+}
+
+#[wasm_bindgen]
+impl DictionaryEngine {
+    /// Like `query_words`, for raw prompt fragments such as `(tag:1.2)`, `[[tag]]`, `-tag` or `artist_\(style\)`.
+    /// Emphasis and weight syntax is peeled off and escaped brackets are unescaped before the lookup.
+    #[wasm_bindgen]
+    pub fn query_fragments(&self, fragments: Vec<String>) -> Vec<FragmentQueryResult> {
+        let start_time = Instant::now();
+
+        let result = fragments
+            .into_iter()
+            .map(|fragment| {
+                let tag = parse_weighted_tag(&fragment);
+                let entries = self.lookup_word(&tag.name);
+                FragmentQueryResult {
+                    fragment,
+                    name: tag.name,
+                    weight: tag.weight,
+                    entries,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        log_performance(
+            "query_fragments total",
+            start_time.elapsed(),
+            Some(&format!(
+                "fragments: {}, total_results: {}",
+                result.len(),
+                result.iter().map(|r| r.entries.len()).sum::<usize>()
+            )),
+        );
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::DictionarySourceInput;
+    use super::super::tests::create_test_csv_data;
+    use super::*;
+
+    #[test]
+    fn test_query_fragments() {
+        let engine = DictionaryEngine::new(create_test_csv_data());
+        let results = engine.query_fragments(vec![
+            "(long_hair:1.2)".to_string(),
+            "[[smiling]]".to_string(),
+            "-1girl".to_string(),
+            "(unknown_tag)".to_string(),
+        ]);
+
+        assert_eq!(results[0].name, "long_hair");
+        assert_eq!(results[0].weight, 1.2);
+        assert_eq!(results[0].entries[0].canonical_key, "long_hair");
+
+        assert_eq!(results[1].name, "smiling");
+        assert!((results[1].weight - 0.81).abs() < 1e-9);
+        assert_eq!(results[1].entries[0].canonical_key, "smile");
+
+        assert_eq!(results[2].weight, 0.0);
+        assert_eq!(results[2].entries[0].canonical_key, "1girl");
+
+        assert_eq!(results[3].name, "unknown_tag");
+        assert!(results[3].entries.is_empty());
+    }
+
+    #[test]
+    fn test_query_escaped_fragments() {
+        let engine = DictionaryEngine::from_sources(
+            vec![DictionarySourceInput {
+                id: "artists".to_string(),
+                text: "artist_(style),1,100,\n".to_string(),
+                ..Default::default()
+            }],
+            None,
+        );
+        let results = engine.query_fragments(vec![
+            "artist_\\(style\\)".to_string(),
+            "(artist_\\(style\\):0.8)".to_string(),
+        ]);
+        for result in &results {
+            assert_eq!(result.name, "artist_(style)");
+            assert_eq!(result.entries[0].canonical_key, "artist_(style)");
+        }
+        assert_eq!(results[1].weight, 0.8);
+        // The escaped form is unknown to a plain lookup
+        assert!(
+            engine.query_words(vec!["artist_\\(style\\)".to_string()])[0]
+                .1
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use super::{
+    DictionaryEngine, FuzzySearchOptions, Instant, Interruption, log_performance, match_indices,
+    sort_ranked_matches,
+};
+use crate::normalize::normalize_for_query;
+
+#[derive(Debug, Clone, Tsify, serde::Serialize)]
+#[tsify(into_wasm_abi)]
+pub struct GroupedCompletionResultEntry {
+    // The best matching term of the tag
+    pub term: String,
+    pub canonical_key: String,
+    pub is_canonical: bool,
+    pub category: i32,
+    pub count: i32,
+    pub score: u32,
+    pub rank: f64,
+    pub is_present: bool,
+    pub is_typo_correction: bool,
+    // Other matched terms of the tag, best first
+    pub matched_terms: Vec<String>,
+    pub aliases: Vec<String>,
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[tsify(optional)]
+    pub match_indices: Option<Vec<u32>>,
+}
+
+// Like `FuzzySearchResponse`, with one result per tag
+#[derive(Debug, Default, Tsify, serde::Serialize)]
+#[tsify(into_wasm_abi)]
+pub struct GroupedFuzzySearchResponse {
+    pub results: Vec<GroupedCompletionResultEntry>,
+    pub truncated: bool,
+    pub aborted: bool,
+}
+
+#[wasm_bindgen]
+impl DictionaryEngine {
+    /// Like `fuzzy_search_with_options`, returning one result per canonical key instead of one per matched term.
+    /// `max_entries` counts distinct tags. Flags partial and aborted searches like `fuzzy_search_with_status`.
+    #[wasm_bindgen]
+    pub fn fuzzy_search_grouped(
+        &mut self,
+        query: &str,
+        options: Option<FuzzySearchOptions>,
+    ) -> GroupedFuzzySearchResponse {
+        let start_time = Instant::now();
+        let options = options.unwrap_or_default();
+
+        if !self.begin_generation(options.generation) {
+            return GroupedFuzzySearchResponse {
+                aborted: true,
+                ..Default::default()
+            };
+        }
+
+        let (pattern, mut matches, interruption) = self.rank_matches(query, &options, start_time);
+        if interruption == Some(Interruption::Superseded) {
+            return GroupedFuzzySearchResponse {
+                aborted: true,
+                ..Default::default()
+            };
+        }
+        let truncated = interruption == Some(Interruption::Deadline);
+        sort_ranked_matches(&mut matches, None);
+
+        // Phase 3: Grouping and result construction
+        let construction_start = Instant::now();
+        let max_entries = options.max_entries.unwrap_or(usize::MAX);
+        let mut results: Vec<GroupedCompletionResultEntry> = Vec::new();
+        let mut group_positions: HashMap<String, usize> = HashMap::new();
+
+        for ranked in matches {
+            let entry = &self.dictionary[ranked.index];
+            let term = match ranked.alias_index {
+                Some(alias_index) => &entry.aliases[alias_index],
+                None => &entry.key,
+            };
+
+            let group_key = normalize_for_query(&entry.key);
+            if let Some(&position) = group_positions.get(&group_key) {
+                let group = &mut results[position];
+                if group.term != *term && !group.matched_terms.contains(term) {
+                    group.matched_terms.push(term.clone());
+                }
+                continue;
+            }
+            // Later matches can still join existing groups
+            if results.len() >= max_entries {
+                continue;
+            }
+
+            group_positions.insert(group_key, results.len());
+            results.push(GroupedCompletionResultEntry {
+                term: term.clone(),
+                canonical_key: entry.key.clone(),
+                is_canonical: ranked.alias_index.is_none(),
+                category: entry.category,
+                count: entry.count,
+                score: ranked.score,
+                rank: ranked.rank,
+                is_present: ranked.is_present,
+                is_typo_correction: ranked.is_typo_correction,
+                matched_terms: Vec::new(),
+                aliases: entry.aliases.clone(),
+                source: entry.source.clone(),
+                match_indices: None,
+            });
+        }
+
+        if options.include_match_indices {
+            for result in &mut results {
+                result.match_indices = Some(match_indices(
+                    &pattern,
+                    &result.term,
+                    &mut self.nucleo_matcher,
+                ));
+            }
+        }
+
+        log_performance(
+            "Grouped result construction",
+            construction_start.elapsed(),
+            Some(&format!("groups: {}", results.len())),
+        );
+
+        log_performance(
+            "fuzzy_search_grouped total",
+            start_time.elapsed(),
+            Some(&format!(
+                "query: '{}', final_results: {}",
+                query,
+                results.len()
+            )),
+        );
+
+        GroupedFuzzySearchResponse {
+            results,
+            truncated,
+            aborted: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::create_test_csv_data;
+    use std::collections::HashSet;
+
+    use super::super::{DictionarySourceInput, DuplicateMergePolicy};
+    use super::*;
+
+    #[test]
+    fn test_grouped_by_canonical_key() {
+        let mut engine = DictionaryEngine::new(create_test_csv_data());
+
+        let flat = engine.fuzzy_search("girl", None, None);
+        let flat_1girl = flat.iter().filter(|r| r.canonical_key == "1girl").count();
+        assert!(flat_1girl > 1);
+
+        let grouped = engine.fuzzy_search_grouped("girl", None).results;
+        let groups = grouped
+            .iter()
+            .filter(|r| r.canonical_key == "1girl")
+            .collect::<Vec<_>>();
+        assert_eq!(groups.len(), 1);
+        let group = groups[0];
+        assert_eq!(group.term, "girl");
+        assert_eq!(group.matched_terms.len(), flat_1girl - 1);
+        assert!(group.matched_terms.contains(&"1girls".to_string()));
+        assert!(!group.matched_terms.contains(&group.term));
+
+        // The best term of each group is the best ranked one of the flat results
+        let best = flat.iter().find(|r| r.canonical_key == "1girl").unwrap();
+        assert_eq!(group.rank, best.rank);
+    }
+
+    #[test]
+    fn test_grouped_max_entries_counts_tags() {
+        let mut engine = DictionaryEngine::new(create_test_csv_data());
+
+        let grouped = engine.fuzzy_search_grouped(
+            "hair",
+            Some(FuzzySearchOptions {
+                max_entries: Some(3),
+                include_match_indices: true,
+                ..Default::default()
+            }),
+        );
+        assert!(!grouped.truncated);
+        let grouped = grouped.results;
+        assert_eq!(grouped.len(), 3);
+        let keys = grouped
+            .iter()
+            .map(|r| r.canonical_key.as_str())
+            .collect::<HashSet<_>>();
+        assert_eq!(keys.len(), 3);
+        assert!(grouped.iter().all(|r| r.match_indices.is_some()));
+        assert!(grouped.windows(2).all(|w| w[0].rank >= w[1].rank));
+    }
+
+    #[test]
+    fn test_grouped_across_sources() {
+        let mut engine = DictionaryEngine::from_sources(
+            vec![
+                DictionarySourceInput {
+                    id: "a".to_string(),
+                    text: "long_hair,0,100,長髪".to_string(),
+                    ..Default::default()
+                },
+                DictionarySourceInput {
+                    id: "b".to_string(),
+                    text: "long hair,0,5,ロングヘア".to_string(),
+                    ..Default::default()
+                },
+            ],
+            None,
+        );
+        assert_eq!(engine.options.merge_policy, DuplicateMergePolicy::KeepAll);
+
+        let grouped = engine.fuzzy_search_grouped("long", None).results;
+        assert_eq!(grouped.len(), 1);
+        assert_eq!(grouped[0].source, "a");
+        assert_eq!(grouped[0].matched_terms, vec!["long hair".to_string()]);
+    }
+
+    #[test]
+    fn test_grouped_flags_partial_results() {
+        let csv = (0..10_000)
+            .map(|i| format!("hair_{i},0,{i},\n"))
+            .collect::<String>();
+        let mut engine = DictionaryEngine::new(vec![csv]);
+
+        let response = engine.fuzzy_search_grouped(
+            "hair",
+            Some(FuzzySearchOptions {
+                deadline_ms: Some(0.0),
+                ..Default::default()
+            }),
+        );
+        assert!(response.truncated);
+        assert!(!response.aborted);
+        assert!(!response.results.is_empty());
+
+        engine.cancel_searches_before(2);
+        let response = engine.fuzzy_search_grouped(
+            "hair",
+            Some(FuzzySearchOptions {
+                generation: Some(1),
+                ..Default::default()
+            }),
+        );
+        assert!(response.aborted);
+        assert!(response.results.is_empty());
+    }
+}
@@ -0,0 +1,584 @@
+use std::collections::HashSet;
+
+use wasm_bindgen::prelude::*;
+
+use super::{
+    DictionaryEngine, DictionaryFormat, DuplicateGroup, DuplicateMergePolicy, Instant, TouchedKeys,
+    index_entry, log_performance, merge_into_haystack, merge_into_key_index, parse_source,
+    sort_index_entries, unindex_entry,
+};
+use crate::normalize::normalize_for_query;
+
+#[wasm_bindgen]
+impl DictionaryEngine {
+    /// Adds a dictionary source after all existing ones, updating the indices in place.
+    /// A source with the same id is replaced.
+    #[wasm_bindgen]
+    pub fn add_source(
+        &mut self,
+        id: String,
+        text: &str,
+        priority: Option<i32>,
+        format: Option<DictionaryFormat>,
+    ) {
+        let start_time = Instant::now();
+
+        self.remove_source(&id);
+
+        let format = format.unwrap_or_default();
+        let (source, entries) =
+            parse_source(text, &format, &id, priority.unwrap_or(0)).into_source(id.clone());
+        let skipped_lines = source.skipped_lines.len();
+
+        let start = self.dictionary.len();
+        self.sources.push(source);
+        self.dictionary.extend(entries);
+
+        let mut touched = TouchedKeys::default();
+        for index in start..self.dictionary.len() {
+            match self.find_merge_target(index) {
+                Some((key, primary)) => self.merge_into(key, primary, index, &mut touched),
+                None => index_entry(
+                    &self.dictionary[index],
+                    index,
+                    &mut self.completion_map,
+                    &mut self.query_map,
+                    &self.options.secondary_keys,
+                    Some(&mut touched),
+                ),
+            }
+        }
+
+        self.refresh_touched_keys(touched);
+
+        log_performance(
+            "add_source",
+            start_time.elapsed(),
+            Some(&format!(
+                "id: '{id}', {} entries added, {skipped_lines} lines skipped",
+                self.dictionary.len() - start
+            )),
+        );
+    }
+
+    /// Removes a dictionary source and all of its entries, updating the indices in place.
+    /// Returns `false` if no source with the given id exists.
+    #[wasm_bindgen]
+    pub fn remove_source(&mut self, id: &str) -> bool {
+        let start_time = Instant::now();
+
+        let Some(position) = self.sources.iter().position(|source| source.id == id) else {
+            return false;
+        };
+        let start: usize = self.sources[..position].iter().map(|s| s.len).sum();
+        let len = self.sources.remove(position).len;
+        let end = start + len;
+        let range = start..end;
+
+        let mut touched = TouchedKeys::default();
+
+        // Undo merges that involve the removed rows. They are merged again once the rows are gone.
+        let broken_groups = self
+            .duplicates
+            .iter()
+            .filter(|(_, group)| group.members.iter().any(|m| range.contains(m)))
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        let mut broken_group_members = Vec::with_capacity(broken_groups.len());
+        for key in &broken_groups {
+            let group = self.duplicates.remove(key).unwrap();
+            let primary = group.members[0];
+            unindex_entry(
+                &self.dictionary[primary],
+                primary,
+                &mut self.completion_map,
+                &mut self.query_map,
+                &self.options.secondary_keys,
+                &mut touched,
+            );
+            group.restore(&mut self.dictionary);
+            broken_group_members.push(group.members);
+        }
+
+        for index in range.clone() {
+            if !self.dictionary[index].shadowed {
+                unindex_entry(
+                    &self.dictionary[index],
+                    index,
+                    &mut self.completion_map,
+                    &mut self.query_map,
+                    &self.options.secondary_keys,
+                    &mut touched,
+                );
+            }
+        }
+
+        // Shift the indices of the entries that followed the removed source.
+        // The shift is uniform, so the relative order of all other entries is unchanged.
+        let shift = |index: &mut usize| {
+            if *index >= end {
+                *index -= len;
+            }
+        };
+        if len > 0 && end < self.dictionary.len() {
+            for indices in self
+                .completion_map
+                .values_mut()
+                .chain(self.query_map.values_mut())
+            {
+                for entry in indices.iter_mut() {
+                    shift(&mut entry.index);
+                }
+            }
+            for group in self.duplicates.values_mut() {
+                group.members.iter_mut().for_each(shift);
+            }
+        }
+
+        self.dictionary.drain(range.clone());
+
+        // Merge what is left of the broken groups
+        for (key, mut members) in broken_groups.into_iter().zip(broken_group_members) {
+            members.retain(|m| !range.contains(m));
+            members.iter_mut().for_each(shift);
+
+            match members.len() {
+                0 => {}
+                1 => {
+                    let index = members[0];
+                    self.dictionary[index].shadowed = false;
+                    index_entry(
+                        &self.dictionary[index],
+                        index,
+                        &mut self.completion_map,
+                        &mut self.query_map,
+                        &self.options.secondary_keys,
+                        Some(&mut touched),
+                    );
+                }
+                _ => {
+                    let group = DuplicateGroup::create(
+                        members,
+                        &mut self.dictionary,
+                        self.options.merge_policy,
+                    );
+                    let primary = group.members[0];
+                    index_entry(
+                        &self.dictionary[primary],
+                        primary,
+                        &mut self.completion_map,
+                        &mut self.query_map,
+                        &self.options.secondary_keys,
+                        Some(&mut touched),
+                    );
+                    self.duplicates.insert(key, group);
+                }
+            }
+        }
+
+        self.refresh_touched_keys(touched);
+
+        log_performance(
+            "remove_source",
+            start_time.elapsed(),
+            Some(&format!("id: '{id}', {len} entries removed")),
+        );
+
+        true
+    }
+
+    // Finds the entry a newly added row has to be merged into under the current merge policy.
+    // Returns the duplicate key and the index of the entry holding the merged data.
+    fn find_merge_target(&self, index: usize) -> Option<(String, usize)> {
+        if self.options.merge_policy == DuplicateMergePolicy::KeepAll {
+            return None;
+        }
+
+        let key = normalize_for_query(&self.dictionary[index].key);
+        let primary = match self.duplicates.get(&key) {
+            Some(group) => group.members[0],
+            None => {
+                self.query_map
+                    .get(&key)?
+                    .iter()
+                    .find(|e| e.alias_index.is_none())?
+                    .index
+            }
+        };
+
+        Some((key, primary))
+    }
+
+    fn merge_into(&mut self, key: String, primary: usize, index: usize, touched: &mut TouchedKeys) {
+        unindex_entry(
+            &self.dictionary[primary],
+            primary,
+            &mut self.completion_map,
+            &mut self.query_map,
+            &self.options.secondary_keys,
+            touched,
+        );
+
+        let mut members = match self.duplicates.remove(&key) {
+            Some(group) => {
+                group.restore(&mut self.dictionary);
+                group.members
+            }
+            None => vec![primary],
+        };
+        members.push(index);
+
+        let group =
+            DuplicateGroup::create(members, &mut self.dictionary, self.options.merge_policy);
+        index_entry(
+            &self.dictionary[primary],
+            primary,
+            &mut self.completion_map,
+            &mut self.query_map,
+            &self.options.secondary_keys,
+            Some(touched),
+        );
+        self.duplicates.insert(key, group);
+    }
+
+    // Restores the ordering of modified map lists and moves modified completion keys
+    // to their current positions in the haystacks
+    fn refresh_touched_keys(&mut self, touched: TouchedKeys) {
+        // Cached positions refer to the haystacks before the update
+        self.search_cache = None;
+
+        for key in &touched.completion {
+            if let Some(indices) = self.completion_map.get_mut(key) {
+                sort_index_entries(&self.dictionary, indices);
+            }
+        }
+        for key in &touched.query {
+            if let Some(indices) = self.query_map.get_mut(key) {
+                indices.sort_by_key(|e| (e.index, e.alias_index));
+            }
+        }
+
+        self.update_haystacks(touched.completion);
+    }
+
+    // Moves the given completion keys to their current positions in the haystacks,
+    // dropping the ones that no longer exist in `completion_map`
+    fn update_haystacks(&mut self, affected_keys: HashSet<String>) {
+        if affected_keys.is_empty() {
+            return;
+        }
+
+        self.completion_haystack_ascii
+            .retain(|key| !affected_keys.contains(key));
+        self.completion_haystack_non_ascii
+            .retain(|key| !affected_keys.contains(key));
+        self.completion_key_index
+            .retain(|key| !affected_keys.contains(key));
+
+        let existing_keys = affected_keys
+            .into_iter()
+            .filter(|key| self.completion_map.contains_key(key))
+            .collect::<Vec<_>>();
+        merge_into_key_index(&mut self.completion_key_index, existing_keys.clone());
+        let (ascii_keys, non_ascii_keys): (Vec<String>, Vec<String>) =
+            existing_keys.into_iter().partition(|key| key.is_ascii());
+
+        merge_into_haystack(
+            &mut self.completion_haystack_ascii,
+            ascii_keys,
+            &self.dictionary,
+            &self.completion_map,
+        );
+        merge_into_haystack(
+            &mut self.completion_haystack_non_ascii,
+            non_ascii_keys,
+            &self.dictionary,
+            &self.completion_map,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{DictionaryEngineOptions, DictionarySourceInput, SecondaryKeyOptions};
+    use super::*;
+
+    const SOURCE_A: &str = r#"1girl,0,5794009,"1girls,女の子,少女,girl,ガール,소녀,女孩"
+long_hair,0,4181922,"ロングヘア,長髪,金髪ロング,长发"
+smile,0,2754486,"smiling,笑顔,미소"
+blonde_hair,0,1482750,"blonde,blond,金髪,金髪ロング,금발""#;
+
+    const SOURCE_B: &str = r#"masterpiece,0,300000,"best_quality,high_quality"
+long_hair,0,1000,"long hair,ロング"
+cat,7,25000,"kitten,feline,neko""#;
+
+    const SOURCE_C: &str = r#"blue_eyes,0,1697952,"碧眼,青い目,金髪碧眼"
+very_long_hair,0,910735,"hair_past_waist,超ロングヘア"
+girl,0,1000,"少女""#;
+
+    fn build(sources: &[(&str, &str, i32)]) -> DictionaryEngine {
+        build_with_policy(sources, DuplicateMergePolicy::KeepAll)
+    }
+
+    fn build_with_policy(
+        sources: &[(&str, &str, i32)],
+        merge_policy: DuplicateMergePolicy,
+    ) -> DictionaryEngine {
+        build_with_options(
+            sources,
+            DictionaryEngineOptions {
+                merge_policy,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn build_with_options(
+        sources: &[(&str, &str, i32)],
+        options: DictionaryEngineOptions,
+    ) -> DictionaryEngine {
+        DictionaryEngine::from_sources(
+            sources
+                .iter()
+                .map(|&(id, csv, priority)| DictionarySourceInput {
+                    id: id.to_string(),
+                    priority,
+                    text: csv.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            Some(options),
+        )
+    }
+
+    fn assert_engines_equivalent(actual: &mut DictionaryEngine, expected: &mut DictionaryEngine) {
+        assert_eq!(actual.dictionary, expected.dictionary);
+        assert_eq!(actual.duplicates, expected.duplicates);
+        assert_eq!(actual.completion_map, expected.completion_map);
+        assert_eq!(actual.query_map, expected.query_map);
+        assert_eq!(
+            actual.completion_haystack_ascii,
+            expected.completion_haystack_ascii
+        );
+        assert_eq!(
+            actual.completion_haystack_non_ascii,
+            expected.completion_haystack_non_ascii
+        );
+        assert_eq!(actual.completion_key_index, expected.completion_key_index);
+        assert_eq!(
+            actual
+                .sources
+                .iter()
+                .map(|s| (&s.id, s.len))
+                .collect::<Vec<_>>(),
+            expected
+                .sources
+                .iter()
+                .map(|s| (&s.id, s.len))
+                .collect::<Vec<_>>()
+        );
+
+        for query in ["girl", "hair", "long", "金髪", "ろんぐ", "rongu", "a"] {
+            let actual_results = actual.fuzzy_search(query, None, Some(true));
+            let expected_results = expected.fuzzy_search(query, None, Some(true));
+            assert_eq!(
+                actual_results
+                    .iter()
+                    .map(|r| (&r.term, &r.canonical_key, &r.source, r.score))
+                    .collect::<Vec<_>>(),
+                expected_results
+                    .iter()
+                    .map(|r| (&r.term, &r.canonical_key, &r.source, r.score))
+                    .collect::<Vec<_>>(),
+                "Mismatch for query: {query}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_add_source_matches_fresh_build() {
+        let mut expected = DictionaryEngine::new(vec![
+            SOURCE_A.to_string(),
+            SOURCE_B.to_string(),
+            SOURCE_C.to_string(),
+        ]);
+
+        let mut engine = DictionaryEngine::new(vec![SOURCE_A.to_string()]);
+        engine.add_source("1".to_string(), SOURCE_B, None, None);
+        engine.add_source("2".to_string(), SOURCE_C, None, None);
+
+        assert_engines_equivalent(&mut engine, &mut expected);
+    }
+
+    #[test]
+    fn test_add_source_to_empty_engine() {
+        let mut expected = build(&[("user", SOURCE_B, 0)]);
+
+        let mut engine = DictionaryEngine::new(vec![]);
+        engine.add_source("user".to_string(), SOURCE_B, None, None);
+
+        assert_engines_equivalent(&mut engine, &mut expected);
+    }
+
+    #[test]
+    fn test_add_source_with_priority_matches_fresh_build() {
+        let mut expected = build(&[("base", SOURCE_A, 0), ("personal", SOURCE_B, 10)]);
+
+        let mut engine = build(&[("base", SOURCE_A, 0)]);
+        engine.add_source("personal".to_string(), SOURCE_B, Some(10), None);
+
+        assert_engines_equivalent(&mut engine, &mut expected);
+    }
+
+    #[test]
+    fn test_remove_source_matches_fresh_build() {
+        let mut expected = build(&[("a", SOURCE_A, 0), ("c", SOURCE_C, 0)]);
+
+        // Remove a source in the middle
+        let mut engine = build(&[("a", SOURCE_A, 0), ("b", SOURCE_B, 0), ("c", SOURCE_C, 0)]);
+        assert!(engine.remove_source("b"));
+        assert_engines_equivalent(&mut engine, &mut expected);
+
+        // Remove the last source
+        let mut engine = build(&[("a", SOURCE_A, 0), ("c", SOURCE_C, 0), ("b", SOURCE_B, 0)]);
+        assert!(engine.remove_source("b"));
+        assert_engines_equivalent(&mut engine, &mut expected);
+
+        // Remove the first source
+        let mut engine = build(&[("b", SOURCE_B, 5), ("a", SOURCE_A, 0), ("c", SOURCE_C, 0)]);
+        assert!(engine.remove_source("b"));
+        assert_engines_equivalent(&mut engine, &mut expected);
+    }
+
+    #[test]
+    fn test_remove_all_sources() {
+        let mut engine = DictionaryEngine::new(vec![SOURCE_A.to_string(), SOURCE_B.to_string()]);
+        assert!(engine.remove_source("0"));
+        assert!(engine.remove_source("1"));
+
+        assert!(engine.dictionary.is_empty());
+        assert!(engine.completion_map.is_empty());
+        assert!(engine.query_map.is_empty());
+        assert!(engine.completion_haystack_ascii.is_empty());
+        assert!(engine.completion_haystack_non_ascii.is_empty());
+        assert!(engine.completion_key_index.is_empty());
+        assert!(engine.fuzzy_search("girl", None, Some(true)).is_empty());
+    }
+
+    #[test]
+    fn test_remove_unknown_source() {
+        let mut engine = DictionaryEngine::new(vec![SOURCE_A.to_string()]);
+        assert!(!engine.remove_source("unknown"));
+        assert_eq!(engine.dictionary.len(), 4);
+    }
+
+    #[test]
+    fn test_add_source_replaces_existing_id() {
+        let mut expected = build(&[("a", SOURCE_A, 0), ("user", SOURCE_C, 0)]);
+
+        let mut engine = build(&[("a", SOURCE_A, 0)]);
+        engine.add_source("user".to_string(), SOURCE_B, None, None);
+        engine.add_source("user".to_string(), SOURCE_C, None, None);
+
+        assert_engines_equivalent(&mut engine, &mut expected);
+    }
+
+    #[test]
+    fn test_add_then_remove_restores_state() {
+        let mut expected = build(&[("a", SOURCE_A, 0), ("b", SOURCE_B, 0)]);
+
+        let mut engine = build(&[("a", SOURCE_A, 0), ("b", SOURCE_B, 0)]);
+        engine.add_source("user".to_string(), SOURCE_C, None, None);
+        assert_eq!(
+            engine.query_words(vec!["金髪碧眼".to_string()])[0].1.len(),
+            1
+        );
+        assert!(engine.remove_source("user"));
+
+        assert_engines_equivalent(&mut engine, &mut expected);
+        assert!(
+            engine.query_words(vec!["金髪碧眼".to_string()])[0]
+                .1
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_incremental_updates_with_merge_policies() {
+        const SOURCE_D: &str = r#"long_hair,1,50,"長い髪"
+smile,0,10,"にっこり"
+long_hair,0,5,"ロン毛""#;
+
+        for merge_policy in [
+            DuplicateMergePolicy::FirstWins,
+            DuplicateMergePolicy::LastWins,
+            DuplicateMergePolicy::SumCounts,
+            DuplicateMergePolicy::UnionAliases,
+        ] {
+            let all = [
+                ("a", SOURCE_A, 0),
+                ("b", SOURCE_B, 0),
+                ("c", SOURCE_C, 0),
+                ("d", SOURCE_D, 3),
+            ];
+
+            // Adding sources one by one
+            let mut expected = build_with_policy(&all, merge_policy);
+            let mut engine = build_with_policy(&all[..1], merge_policy);
+            for &(id, csv, priority) in &all[1..] {
+                engine.add_source(id.to_string(), csv, Some(priority), None);
+            }
+            assert_engines_equivalent(&mut engine, &mut expected);
+
+            // Removing each source in turn, including the ones holding merged entries
+            for removed in 0..all.len() {
+                let remaining = all
+                    .iter()
+                    .enumerate()
+                    .filter(|&(i, _)| i != removed)
+                    .map(|(_, &source)| source)
+                    .collect::<Vec<_>>();
+                let mut expected = build_with_policy(&remaining, merge_policy);
+
+                let mut engine = build_with_policy(&all, merge_policy);
+                assert!(engine.remove_source(all[removed].0));
+                assert_engines_equivalent(&mut engine, &mut expected);
+            }
+
+            // Removing all other sources leaves only the duplicates within the last one
+            let mut expected = build_with_policy(&all[3..], merge_policy);
+            let mut engine = build_with_policy(&all, merge_policy);
+            for &(id, _, _) in &all[..3] {
+                assert!(engine.remove_source(id));
+            }
+            assert_engines_equivalent(&mut engine, &mut expected);
+        }
+    }
+
+    #[test]
+    fn test_incremental_updates_with_secondary_keys() {
+        let options = DictionaryEngineOptions {
+            secondary_keys: SecondaryKeyOptions {
+                romaji: true,
+                pinyin: true,
+                hangul_initials: true,
+            },
+            ..Default::default()
+        };
+
+        let mut engine = build_with_options(&[("0", SOURCE_A, 0)], options.clone());
+        engine.add_source("1".to_string(), SOURCE_B, None, None);
+        engine.add_source("2".to_string(), SOURCE_C, None, None);
+        let mut expected = build_with_options(
+            &[("0", SOURCE_A, 0), ("1", SOURCE_B, 0), ("2", SOURCE_C, 0)],
+            options.clone(),
+        );
+        assert!(engine.completion_map.contains_key("rongu"));
+        assert_engines_equivalent(&mut engine, &mut expected);
+
+        engine.remove_source("1");
+        let mut expected = build_with_options(&[("0", SOURCE_A, 0), ("2", SOURCE_C, 0)], options);
+        assert!(!engine.completion_map.contains_key("rongu"));
+        assert_engines_equivalent(&mut engine, &mut expected);
+    }
+}
@@ -0,0 +1,171 @@
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use super::canonicalize::{PromptReplacement, PromptRewriter};
+use super::{DictionaryEngine, Instant, log_performance};
+use crate::normalize::normalize_for_query;
+use crate::prompt::tag_tokens;
+use crate::script::{HanForm, Script, han_form, text_script};
+
+// Script to render tags in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Tsify, serde::Deserialize)]
+#[tsify(from_wasm_abi)]
+#[serde(rename_all = "kebab-case")]
+pub enum PromptScript {
+    // Kana or kanji
+    Japanese,
+    Hangul,
+    Han,
+}
+
+impl PromptScript {
+    fn accepts(self, script: Option<Script>) -> bool {
+        match self {
+            PromptScript::Japanese => matches!(script, Some(Script::Kana | Script::Han)),
+            PromptScript::Hangul => script == Some(Script::Hangul),
+            PromptScript::Han => script == Some(Script::Han),
+        }
+    }
+
+    // How well the Han characters of an alias in the script suit it: -1 with any character of another
+    // language, 1 with one telling the alias is in this language, 0 otherwise. `Han` is read as
+    // Simplified Chinese.
+    fn affinity(self, alias: &str) -> i32 {
+        let forms = alias.chars().filter_map(han_form);
+        match self {
+            PromptScript::Han => forms
+                .map(|form| if form == HanForm::Chinese { 1 } else { -1 })
+                .min()
+                .unwrap_or(0),
+            PromptScript::Japanese => forms
+                .map(|form| if form == HanForm::Japanese { 0 } else { -1 })
+                .min()
+                .unwrap_or(0),
+            PromptScript::Hangul => 0,
+        }
+    }
+}
+
+#[derive(Debug, Tsify, serde::Serialize)]
+#[tsify(into_wasm_abi)]
+pub struct LocalizedPrompt {
+    pub text: String,
+    pub replacements: Vec<PromptReplacement>,
+}
+
+impl DictionaryEngine {
+    // The alias of a canonical tag best suiting the script, across every entry of the tag. Han aliases
+    // found inside a kana alias, like `笑` in `笑い`, are taken for Japanese words and come after the
+    // others. Ties go to the first alias.
+    fn localized_alias(&self, canonical_key: &str, script: PromptScript) -> Option<&str> {
+        let aliases = self
+            .query_map
+            .get(&normalize_for_query(canonical_key))?
+            .iter()
+            .filter(|entry| entry.alias_index.is_none())
+            .flat_map(|entry| &self.dictionary[entry.index].aliases)
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        let is_japanese_word = |alias: &str| {
+            script == PromptScript::Han
+                && aliases
+                    .iter()
+                    .any(|other| text_script(other) == Some(Script::Kana) && other.contains(alias))
+        };
+
+        let mut best: Option<(&str, (i32, bool))> = None;
+        for &alias in aliases
+            .iter()
+            .filter(|alias| script.accepts(text_script(alias)))
+        {
+            let rank = (script.affinity(alias), !is_japanese_word(alias));
+            if best.is_none_or(|(_, best_rank)| rank > best_rank) {
+                best = Some((alias, rank));
+            }
+        }
+        best.map(|(alias, _)| alias)
+    }
+}
+
+#[wasm_bindgen]
+impl DictionaryEngine {
+    /// Renders every tag resolving to a single canonical tag with its alias best suiting the script. Tags already
+    /// written in the script are kept. This is meant for display only: the replacements can be shown over the
+    /// prompt, which itself stays unchanged for compilation.
+    #[wasm_bindgen]
+    pub fn localize_prompt(&self, text: &str, preferred_script: PromptScript) -> LocalizedPrompt {
+        let start_time = Instant::now();
+
+        let mut rewriter = PromptRewriter::new(text);
+        for token in tag_tokens(text) {
+            if preferred_script.accepts(text_script(&token.tag.name)) {
+                continue;
+            }
+            if let [key] = self.canonical_keys(&token.tag.name).as_slice()
+                && let Some(alias) = self.localized_alias(key, preferred_script)
+            {
+                rewriter.replace(token.tag.name_range, alias.to_string());
+            }
+        }
+        let (output, replacements) = rewriter.finish();
+
+        log_performance(
+            "localize_prompt total",
+            start_time.elapsed(),
+            Some(&format!(
+                "script: {:?}, replacements: {}",
+                preferred_script,
+                replacements.len()
+            )),
+        );
+
+        LocalizedPrompt {
+            text: output,
+            replacements,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::create_test_csv_data;
+    use super::*;
+
+    #[test]
+    fn test_localize_prompt() {
+        let engine = DictionaryEngine::new(create_test_csv_data());
+        let text = "1girl, (long_hair:1.2), blue_eyes // smile\n-smiling, unknown_tag";
+
+        let japanese = engine.localize_prompt(text, PromptScript::Japanese);
+        assert_eq!(
+            japanese.text,
+            "女の子, (ロングヘアー:1.2), 碧眼 // smile\n-笑い, unknown_tag"
+        );
+        assert_eq!(japanese.replacements.len(), 4);
+        assert_eq!(japanese.replacements[1].original, "long_hair");
+
+        let korean = engine.localize_prompt(text, PromptScript::Hangul);
+        assert_eq!(
+            korean.text,
+            "소녀, (long_hair:1.2), 파란눈 // smile\n-미소, unknown_tag"
+        );
+
+        let chinese = engine.localize_prompt(text, PromptScript::Han);
+        assert_eq!(
+            chinese.text,
+            "女孩, (长发:1.2), 蓝眼睛 // smile\n-笑容, unknown_tag"
+        );
+    }
+
+    #[test]
+    fn test_localize_keeps_tags_in_script() {
+        let engine = DictionaryEngine::new(create_test_csv_data());
+        let result = engine.localize_prompt("長髪, 金髪", PromptScript::Japanese);
+        assert_eq!(result.text, "長髪, 金髪");
+        assert!(result.replacements.is_empty());
+
+        // Tags in another script are localized through their canonical tag
+        let result = engine.localize_prompt("소녀", PromptScript::Japanese);
+        assert_eq!(result.text, "女の子");
+    }
+}
@@ -0,0 +1,240 @@
+use std::borrow::Cow;
+
+use tsify::Tsify;
+
+use super::DictionaryEngine;
+
+// How the query is matched against completion keys
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Tsify, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SearchMode {
+    // Subsequence matching scored by nucleo
+    #[default]
+    Fuzzy,
+    // Keys starting with the query
+    Prefix,
+    // Keys containing the query
+    Substring,
+    // Keys equal to the query
+    Exact,
+}
+
+// Whether the query matches case-sensitively, like nucleo's smart case: only if it has an uppercase char
+fn is_case_sensitive(query: &str) -> bool {
+    query.chars().any(char::is_uppercase)
+}
+
+// The key, lowercased for comparison with a query without uppercase chars
+fn fold_case(key: &str, case_sensitive: bool) -> Cow<'_, str> {
+    if case_sensitive || !key.chars().any(char::is_uppercase) {
+        Cow::Borrowed(key)
+    } else {
+        Cow::Owned(key.to_lowercase())
+    }
+}
+
+// Keys of the sorted index starting with the prefix followed by the rest of the query, where each
+// char of the rest also matches its uppercase form. Every case variant present in the index
+// narrows its own range, so only ranges holding matching keys are searched.
+fn case_insensitive_prefix<'a>(
+    index: &'a [String],
+    prefix: &mut String,
+    rest: &[char],
+    found: &mut Vec<&'a String>,
+) {
+    let Some((&c, rest)) = rest.split_first() else {
+        found.extend(index);
+        return;
+    };
+    let mut upper = c.to_uppercase();
+    let upper = match (upper.next(), upper.next()) {
+        (Some(upper), None) if upper != c => Some(upper),
+        _ => None,
+    };
+    for variant in std::iter::once(c).chain(upper) {
+        prefix.push(variant);
+        let start = index.partition_point(|key| key.as_str() < prefix.as_str());
+        let end = start + index[start..].partition_point(|key| key.starts_with(prefix.as_str()));
+        case_insensitive_prefix(&index[start..end], prefix, rest, found);
+        prefix.pop();
+    }
+}
+
+impl DictionaryEngine {
+    // Completion keys matching the trimmed query under a non-fuzzy mode, all with a score of 0.
+    // Case is ignored unless the query has an uppercase char, like in fuzzy mode. An empty query
+    // matches nothing, and non-ASCII keys are only included when `try_non_ascii` is set.
+    pub(super) fn literal_matches(
+        &self,
+        completion_query: &str,
+        mode: SearchMode,
+        try_non_ascii: bool,
+    ) -> Vec<(&str, u32)> {
+        let query = completion_query.trim();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let case_sensitive = is_case_sensitive(query);
+        let keys: Vec<&String> = match mode {
+            SearchMode::Fuzzy => Vec::new(),
+            SearchMode::Prefix | SearchMode::Exact if case_sensitive => {
+                let start = self
+                    .completion_key_index
+                    .partition_point(|key| key.as_str() < query);
+                self.completion_key_index[start..]
+                    .iter()
+                    .take_while(|key| key.starts_with(query))
+                    .filter(|key| mode == SearchMode::Prefix || key.as_str() == query)
+                    .collect()
+            }
+            SearchMode::Prefix | SearchMode::Exact => {
+                let mut found = Vec::new();
+                let query_chars = query.chars().collect::<Vec<_>>();
+                case_insensitive_prefix(
+                    &self.completion_key_index,
+                    &mut String::new(),
+                    &query_chars,
+                    &mut found,
+                );
+                if mode == SearchMode::Exact {
+                    found.retain(|key| fold_case(key, false) == query);
+                }
+                found
+            }
+            SearchMode::Substring => self
+                .completion_haystack_ascii
+                .iter()
+                .chain(self.completion_haystack_non_ascii.iter())
+                .filter(|key| fold_case(key, case_sensitive).contains(query))
+                .collect(),
+        };
+
+        keys.into_iter()
+            .filter(|key| try_non_ascii || key.is_ascii())
+            .map(|key| (key.as_str(), 0))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::create_test_csv_data;
+    use super::super::{DictionaryEngine, DictionarySourceInput, FuzzySearchOptions};
+    use super::*;
+
+    fn search(engine: &mut DictionaryEngine, query: &str, mode: SearchMode) -> Vec<String> {
+        engine
+            .fuzzy_search_with_options(
+                query,
+                Some(FuzzySearchOptions {
+                    mode,
+                    typo_fallback_threshold: Some(0),
+                    ..Default::default()
+                }),
+            )
+            .into_iter()
+            .map(|r| r.term)
+            .collect()
+    }
+
+    #[test]
+    fn test_search_modes() {
+        let mut engine = DictionaryEngine::new(create_test_csv_data());
+
+        let prefix = search(&mut engine, "blue", SearchMode::Prefix);
+        assert!(!prefix.is_empty());
+        assert!(prefix.iter().all(|term| term.starts_with("blue")));
+        assert!(search(&mut engine, "lue", SearchMode::Prefix).is_empty());
+
+        let substring = search(&mut engine, "hair", SearchMode::Substring);
+        assert!(substring.contains(&"long_hair".to_string()));
+        assert!(substring.iter().all(|term| term.contains("hair")));
+        assert!(search(&mut engine, "lng", SearchMode::Substring).is_empty());
+
+        assert_eq!(
+            search(&mut engine, "long_hair", SearchMode::Exact),
+            vec!["long_hair"]
+        );
+        assert!(search(&mut engine, "long", SearchMode::Exact).is_empty());
+
+        // Fuzzy mode still matches subsequences
+        assert!(!search(&mut engine, "lng", SearchMode::Fuzzy).is_empty());
+    }
+
+    #[test]
+    fn test_literal_modes_without_typo_corrections() {
+        let mut engine = DictionaryEngine::new(create_test_csv_data());
+        let search = |engine: &mut DictionaryEngine, query, mode| {
+            engine.fuzzy_search_with_options(
+                query,
+                Some(FuzzySearchOptions {
+                    mode,
+                    ..Default::default()
+                }),
+            )
+        };
+
+        // Too few matches for the default threshold, yet only keys starting with the query
+        let results = search(&mut engine, "blue", SearchMode::Prefix);
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|r| r.term.starts_with("blue")));
+        assert!(results.iter().all(|r| !r.is_typo_correction));
+
+        let results = search(&mut engine, "smil", SearchMode::Substring);
+        assert!(results.iter().all(|r| r.term.contains("smil")));
+        assert!(search(&mut engine, "lnog", SearchMode::Substring).is_empty());
+
+        // Fuzzy mode falls back to corrections
+        let results = search(&mut engine, "lnog_hair", SearchMode::Fuzzy);
+        assert!(results.iter().any(|r| r.is_typo_correction));
+    }
+
+    #[test]
+    fn test_search_modes_non_ascii() {
+        let mut engine = DictionaryEngine::new(create_test_csv_data());
+
+        assert!(search(&mut engine, "長", SearchMode::Prefix).contains(&"長髪".to_string()));
+        assert!(search(&mut engine, "髪", SearchMode::Substring).contains(&"長髪".to_string()));
+        assert_eq!(search(&mut engine, "長髪", SearchMode::Exact), vec!["長髪"]);
+    }
+
+    #[test]
+    fn test_search_modes_smart_case() {
+        let mut engine = DictionaryEngine::from_sources(
+            vec![DictionarySourceInput {
+                id: "base".to_string(),
+                text: "blue_eyes,0,100,\nBlue_archive,4,50,\nblUE,0,10,\n".to_string(),
+                ..Default::default()
+            }],
+            None,
+        );
+        let mut found = |query, mode| {
+            let mut terms = search(&mut engine, query, mode);
+            terms.sort();
+            terms
+        };
+
+        // Queries without uppercase chars ignore case
+        assert_eq!(
+            found("blue", SearchMode::Prefix),
+            vec!["Blue_archive", "blUE", "blue_eyes"]
+        );
+        assert_eq!(found("blue", SearchMode::Exact), vec!["blUE"]);
+        assert_eq!(found("e a", SearchMode::Substring), vec!["Blue_archive"]);
+
+        // Queries with uppercase chars match case
+        assert_eq!(found("Blue", SearchMode::Prefix), vec!["Blue_archive"]);
+        assert!(found("BLUE", SearchMode::Exact).is_empty());
+        assert_eq!(found("UE", SearchMode::Substring), vec!["blUE"]);
+        assert_eq!(found("Blue", SearchMode::Fuzzy)[0], "Blue_archive");
+    }
+
+    #[test]
+    fn test_empty_query_in_literal_modes() {
+        let mut engine = DictionaryEngine::new(create_test_csv_data());
+        for mode in [SearchMode::Prefix, SearchMode::Substring, SearchMode::Exact] {
+            assert!(search(&mut engine, "", mode).is_empty(), "{mode:?}");
+            assert!(search(&mut engine, "  ", mode).is_empty(), "{mode:?}");
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use js_sys::Uint8Array;
+use wasm_bindgen::prelude::*;
+
+use super::{
+    DictionaryEngine, DictionaryEngineOptions, DictionaryFormat, DictionarySourceInput, Instant,
+    log_performance,
+};
+use crate::coding::{PayloadEncoding, decode_payload};
+
+// Guards against decompression bombs when no limit is configured
+const DEFAULT_MAX_PAYLOAD_SIZE: usize = 256 * 1024 * 1024;
+
+fn max_payload_size(options: &DictionaryEngineOptions) -> usize {
+    options.max_payload_size.unwrap_or(DEFAULT_MAX_PAYLOAD_SIZE)
+}
+
+impl DictionaryEngine {
+    pub(super) fn from_payload_slices(
+        payloads: &[&[u8]],
+        encoding: PayloadEncoding,
+        options: DictionaryEngineOptions,
+    ) -> Result<DictionaryEngine, String> {
+        let start_time = Instant::now();
+        let max_size = max_payload_size(&options);
+
+        let inputs = payloads
+            .iter()
+            .enumerate()
+            .map(|(index, payload)| {
+                let text = decode_payload(payload, encoding, max_size)
+                    .map_err(|err| format!("Source {index}: {err}"))?;
+                Ok(DictionarySourceInput {
+                    id: index.to_string(),
+                    text,
+                    ..Default::default()
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        log_performance(
+            "Payload decoding",
+            start_time.elapsed(),
+            Some(&format!(
+                "{} bytes",
+                payloads.iter().map(|p| p.len()).sum::<usize>()
+            )),
+        );
+
+        Ok(Self::from_sources(inputs, Some(options)))
+    }
+}
+
+#[wasm_bindgen]
+impl DictionaryEngine {
+    /// Creates an engine from binary payloads the same way `new` does from strings.
+    /// Each payload may be plain UTF-8, raw Brotli or `encode_text` output, and is decoded in Rust.
+    #[wasm_bindgen]
+    pub fn from_payloads(
+        payloads: Vec<Uint8Array>,
+        encoding: Option<PayloadEncoding>,
+        options: Option<DictionaryEngineOptions>,
+    ) -> Result<DictionaryEngine, String> {
+        let payloads = payloads.iter().map(Uint8Array::to_vec).collect::<Vec<_>>();
+        let payloads = payloads.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        Self::from_payload_slices(
+            &payloads,
+            encoding.unwrap_or_default(),
+            options.unwrap_or_default(),
+        )
+    }
+
+    /// Binary counterpart of `add_source`. On error the engine is left unchanged.
+    #[wasm_bindgen]
+    pub fn add_source_payload(
+        &mut self,
+        id: String,
+        payload: &[u8],
+        encoding: Option<PayloadEncoding>,
+        priority: Option<i32>,
+        format: Option<DictionaryFormat>,
+    ) -> Result<(), String> {
+        let text = decode_payload(
+            payload,
+            encoding.unwrap_or_default(),
+            max_payload_size(&self.options),
+        )?;
+        self.add_source(id, &text, priority, format);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::super::tests::create_test_csv_data;
+    use super::*;
+
+    fn compress(text: &str) -> Vec<u8> {
+        let mut writer = brotli::CompressorWriter::new(Vec::new(), 4096, 11, 22);
+        writer.write_all(text.as_bytes()).unwrap();
+        writer.into_inner()
+    }
+
+    #[test]
+    fn test_from_payloads_matches_new() {
+        let csvs = create_test_csv_data();
+        let compressed = csvs.iter().map(|csv| compress(csv)).collect::<Vec<_>>();
+        let payloads = compressed.iter().map(Vec::as_slice).collect::<Vec<_>>();
+
+        let expected = DictionaryEngine::new(csvs);
+        let engine = DictionaryEngine::from_payload_slices(
+            &payloads,
+            PayloadEncoding::Auto,
+            DictionaryEngineOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(engine.dictionary, expected.dictionary);
+        assert_eq!(engine.completion_map, expected.completion_map);
+        assert_eq!(engine.query_map, expected.query_map);
+    }
+
+    #[test]
+    fn test_payload_size_limit() {
+        let payload = compress(&"tag,0,1,\n".repeat(1000));
+        let options = DictionaryEngineOptions {
+            max_payload_size: Some(100),
+            ..Default::default()
+        };
+
+        let error =
+            DictionaryEngine::from_payload_slices(&[&payload], PayloadEncoding::Auto, options)
+                .err()
+                .unwrap();
+        assert!(error.starts_with("Source 0"));
+        assert!(error.contains("exceeds the limit"));
+    }
+
+    #[test]
+    fn test_add_source_payload() {
+        let mut engine = DictionaryEngine::new(vec!["cat,0,1,neko".to_string()]);
+        engine
+            .add_source_payload(
+                "user".to_string(),
+                &compress("dog,0,2,inu"),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(engine.query_words(vec!["inu".to_string()])[0].1.len(), 1);
+
+        let result = engine.add_source_payload(
+            "broken".to_string(),
+            b"\xff\xfe",
+            Some(PayloadEncoding::Brotli),
+            None,
+            None,
+        );
+        assert!(result.is_err());
+        assert_eq!(engine.sources.len(), 2);
+    }
+}
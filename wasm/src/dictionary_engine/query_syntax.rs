@@ -0,0 +1,190 @@
+use super::FuzzySearchOptions;
+
+// Category names, mirroring `categoryToId` in `src/lib/core/category.ts`
+const CATEGORY_NAMES: [(&str, i32); 5] = [
+    ("general", 0),
+    ("artist", 1),
+    ("copyright", 3),
+    ("character", 4),
+    ("meta", 5),
+];
+
+// Filters typed inline in a search query
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct InlineFilters {
+    // From `c:name`, `category:name` or `#name`, any of which may match
+    categories: Vec<i32>,
+    // From `>n` or `>=n`
+    min_count: Option<i32>,
+    // From `!term`
+    exclude_terms: Vec<String>,
+}
+
+// A category by id or by an unambiguous prefix of its name
+fn parse_category(name: &str) -> Option<i32> {
+    if let Ok(id) = name.parse() {
+        return Some(id);
+    }
+    let name = name.to_lowercase();
+    let mut candidates = CATEGORY_NAMES
+        .iter()
+        .filter(|(category, _)| !name.is_empty() && category.starts_with(&name));
+    match (candidates.next(), candidates.next()) {
+        (Some(&(_, id)), None) => Some(id),
+        _ => None,
+    }
+}
+
+// A count, optionally with a `k` or `m` suffix
+fn parse_count(text: &str) -> Option<i32> {
+    let lower = text.to_lowercase();
+    let (number, multiplier) = if let Some(number) = lower.strip_suffix('k') {
+        (number, 1_000.0)
+    } else if let Some(number) = lower.strip_suffix('m') {
+        (number, 1_000_000.0)
+    } else {
+        (lower.as_str(), 1.0)
+    };
+    let count = number.parse::<f64>().ok()? * multiplier;
+    (count.is_finite() && count >= 0.0).then(|| count.min(i32::MAX as f64) as i32)
+}
+
+impl InlineFilters {
+    // Applies one whitespace-separated token, returning false if it is not a filter
+    fn apply_token(&mut self, token: &str) -> bool {
+        let category = token
+            .strip_prefix("c:")
+            .or_else(|| token.strip_prefix("category:"))
+            .or_else(|| token.strip_prefix('#'));
+        if let Some(id) = category.and_then(parse_category) {
+            self.categories.push(id);
+            return true;
+        }
+
+        let min_count = if let Some(count) = token.strip_prefix(">=") {
+            parse_count(count)
+        } else if let Some(count) = token.strip_prefix('>') {
+            parse_count(count).map(|count| count.saturating_add(1))
+        } else {
+            None
+        };
+        if let Some(min_count) = min_count {
+            self.min_count = Some(self.min_count.map_or(min_count, |m| m.max(min_count)));
+            return true;
+        }
+
+        // Tags like `!` or `!?` are searched for as is
+        if let Some(term) = token.strip_prefix('!')
+            && term.chars().any(char::is_alphanumeric)
+        {
+            self.exclude_terms.push(term.to_string());
+            return true;
+        }
+
+        false
+    }
+
+    // Narrows the options with the inline filters
+    pub(super) fn apply(self, options: &FuzzySearchOptions) -> FuzzySearchOptions {
+        let mut options = options.clone();
+        if !self.categories.is_empty() {
+            options.categories = Some(match options.categories {
+                Some(categories) => self
+                    .categories
+                    .into_iter()
+                    .filter(|category| categories.contains(category))
+                    .collect(),
+                None => self.categories,
+            });
+        }
+        if let Some(min_count) = self.min_count {
+            options.min_count = Some(options.min_count.map_or(min_count, |m| m.max(min_count)));
+        }
+        if !self.exclude_terms.is_empty() {
+            options
+                .exclude_terms
+                .get_or_insert_default()
+                .extend(self.exclude_terms);
+        }
+        options
+    }
+}
+
+// Splits a query into the text to match and its inline filters. The query is returned unchanged
+// when it has no filters, otherwise its remaining tokens are joined by single spaces.
+pub(super) fn parse_query(query: &str) -> (String, InlineFilters) {
+    let mut filters = InlineFilters::default();
+    let remaining = query
+        .split_whitespace()
+        .filter(|token| !filters.apply_token(token))
+        .collect::<Vec<_>>();
+
+    if filters == InlineFilters::default() {
+        (query.to_string(), filters)
+    } else {
+        (remaining.join(" "), filters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::create_test_csv_data;
+    use super::super::{DictionaryEngine, FuzzySearchOptions};
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        let (text, filters) = parse_query("c:char rem");
+        assert_eq!(text, "rem");
+        assert_eq!(filters.categories, vec![4]);
+
+        let (text, filters) = parse_query("#artist foo #3");
+        assert_eq!(text, "foo");
+        assert_eq!(filters.categories, vec![1, 3]);
+
+        let (text, filters) = parse_query(">10000 long hair >=5k");
+        assert_eq!(text, "long hair");
+        assert_eq!(filters.min_count, Some(10001));
+
+        let (text, filters) = parse_query("hair !black !");
+        assert_eq!(text, "hair !");
+        assert_eq!(filters.exclude_terms, vec!["black"]);
+
+        // Tokens that are not valid filters are matched as text
+        for query in [" >_< ", "c:", "#c", "re:zero", ">abc", "long_hair "] {
+            let (text, filters) = parse_query(query);
+            assert_eq!(text, query);
+            assert_eq!(filters, InlineFilters::default());
+        }
+    }
+
+    #[test]
+    fn test_apply_narrows_options() {
+        let options = FuzzySearchOptions {
+            categories: Some(vec![0, 4]),
+            min_count: Some(100),
+            ..Default::default()
+        };
+        let (_, filters) = parse_query("#char #artist >10 !black");
+        let options = filters.apply(&options);
+        assert_eq!(options.categories, Some(vec![4]));
+        assert_eq!(options.min_count, Some(100));
+        assert_eq!(options.exclude_terms, Some(vec!["black".to_string()]));
+    }
+
+    #[test]
+    fn test_inline_filters_in_search() {
+        let mut engine = DictionaryEngine::new(create_test_csv_data());
+
+        let results = engine.fuzzy_search("#meta hair", None, None);
+        assert!(results.iter().all(|r| r.category == 5));
+
+        let results = engine.fuzzy_search(">3000000 hair", None, None);
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|r| r.count > 3_000_000));
+
+        let results = engine.fuzzy_search("hair !long", None, None);
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|r| !r.term.contains("long")));
+    }
+}
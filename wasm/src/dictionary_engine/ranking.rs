@@ -0,0 +1,279 @@
+use std::cmp::Ordering;
+
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use super::DictionaryEngine;
+
+// Weights of the model ranking fuzzy search results. A result's rank is
+// `match_score * score + popularity * ln(1 + count) + priority * source_priority`, plus the match
+// bonus, minus the alias penalty. Results are ordered by rank, with typo corrections after every
+// other match.
+#[derive(Debug, Clone, Copy, PartialEq, Tsify, serde::Serialize, serde::Deserialize)]
+#[tsify(from_wasm_abi)]
+#[serde(default)]
+pub struct RankingWeights {
+    // Multiplier of the nucleo score
+    #[tsify(optional)]
+    pub match_score: f64,
+    // Multiplier of the log-scaled count
+    #[tsify(optional)]
+    pub popularity: f64,
+    // Multiplier of the priority of the entry's source
+    #[tsify(optional)]
+    pub priority: f64,
+    // Added when the term equals the query after normalization
+    #[tsify(optional)]
+    pub exact_match_bonus: f64,
+    // Added when the term starts with the query after normalization, without being equal to it
+    #[tsify(optional)]
+    pub prefix_match_bonus: f64,
+    // Subtracted when the term is an alias rather than the canonical key
+    #[tsify(optional)]
+    pub alias_penalty: f64,
+    // Subtracted for tags already in the prompt, when they are demoted rather than excluded
+    #[tsify(optional)]
+    pub present_tag_penalty: f64,
+    // Subtracted per edit for typo corrections, which only orders them among themselves
+    #[tsify(optional)]
+    pub typo_penalty: f64,
+}
+
+impl Default for RankingWeights {
+    fn default() -> Self {
+        RankingWeights {
+            match_score: 1.0,
+            popularity: 2.0,
+            priority: 100.0,
+            exact_match_bonus: 1000.0,
+            prefix_match_bonus: 20.0,
+            alias_penalty: 10.0,
+            present_tag_penalty: 20.0,
+            typo_penalty: 30.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum MatchKind {
+    Exact,
+    Prefix,
+    Other,
+}
+
+impl MatchKind {
+    // Both arguments are expected to be normalized for auto completion
+    pub(super) fn of(completion_key: &str, completion_query: &str) -> MatchKind {
+        let query = completion_query.trim();
+        if query.is_empty() {
+            MatchKind::Other
+        } else if completion_key == query {
+            MatchKind::Exact
+        } else if completion_key.starts_with(query) {
+            MatchKind::Prefix
+        } else {
+            MatchKind::Other
+        }
+    }
+}
+
+impl RankingWeights {
+    pub(super) fn rank(
+        &self,
+        score: u32,
+        count: i32,
+        priority: i32,
+        is_canonical: bool,
+        kind: MatchKind,
+    ) -> f64 {
+        let bonus = match kind {
+            MatchKind::Exact => self.exact_match_bonus,
+            MatchKind::Prefix => self.prefix_match_bonus,
+            MatchKind::Other => 0.0,
+        };
+        let penalty = if is_canonical {
+            0.0
+        } else {
+            self.alias_penalty
+        };
+
+        self.match_score * score as f64
+            + self.popularity * (count.max(0) as f64).ln_1p()
+            + self.priority * priority as f64
+            + bonus
+            - penalty
+    }
+}
+
+// A matched dictionary entry, before the result is constructed
+#[derive(Debug, Clone, Copy)]
+pub(super) struct RankedMatch {
+    pub(super) index: usize,
+    pub(super) alias_index: Option<usize>,
+    pub(super) score: u32,
+    pub(super) rank: f64,
+    pub(super) is_present: bool,
+    pub(super) is_typo_correction: bool,
+    // Position in the order produced by the matcher, used to break ties
+    pub(super) order: usize,
+}
+
+impl RankedMatch {
+    fn compare(&self, other: &RankedMatch) -> Ordering {
+        self.is_typo_correction
+            .cmp(&other.is_typo_correction)
+            .then_with(|| other.rank.total_cmp(&self.rank))
+            .then_with(|| self.order.cmp(&other.order))
+    }
+}
+
+// Sorts matches by rank, keeping only the best `max_entries`
+pub(super) fn sort_ranked_matches(matches: &mut Vec<RankedMatch>, max_entries: Option<usize>) {
+    if let Some(max) = max_entries
+        && max < matches.len()
+    {
+        if max == 0 {
+            matches.clear();
+            return;
+        }
+        matches.select_nth_unstable_by(max - 1, RankedMatch::compare);
+        matches.truncate(max);
+    }
+    matches.sort_unstable_by(RankedMatch::compare);
+}
+
+#[wasm_bindgen]
+impl DictionaryEngine {
+    /// Replaces the weights used to rank fuzzy search results.
+    #[wasm_bindgen]
+    pub fn set_ranking_weights(&mut self, weights: RankingWeights) {
+        self.options.ranking = weights;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::create_test_csv_data;
+    use super::super::{DictionaryEngine, DictionarySourceInput};
+    use super::*;
+
+    #[test]
+    fn test_match_kind() {
+        assert_eq!(MatchKind::of("long hair", "long hair "), MatchKind::Exact);
+        assert_eq!(MatchKind::of("long hair", "long"), MatchKind::Prefix);
+        assert_eq!(MatchKind::of("long hair", "hair"), MatchKind::Other);
+        assert_eq!(MatchKind::of("long hair", " "), MatchKind::Other);
+    }
+
+    #[test]
+    fn test_rank() {
+        let weights = RankingWeights::default();
+        let canonical = weights.rank(100, 0, 0, true, MatchKind::Other);
+        assert_eq!(canonical, 100.0);
+        assert_eq!(weights.rank(100, 0, 0, false, MatchKind::Other), 90.0);
+        assert_eq!(weights.rank(100, 0, 0, true, MatchKind::Exact), 1100.0);
+        assert_eq!(weights.rank(100, 0, 2, true, MatchKind::Other), 300.0);
+        assert!(weights.rank(100, 1000, 0, true, MatchKind::Other) > canonical);
+        // Negative counts do not lower the rank
+        assert_eq!(weights.rank(100, -5, 0, true, MatchKind::Other), canonical);
+    }
+
+    #[test]
+    fn test_sort_ranked_matches() {
+        let ranks = [3.0, 5.0, 1.0, 5.0, 4.0];
+        let matches = ranks
+            .iter()
+            .enumerate()
+            .map(|(order, &rank)| RankedMatch {
+                index: order,
+                alias_index: None,
+                score: 0,
+                rank,
+                is_present: false,
+                is_typo_correction: false,
+                order,
+            })
+            .collect::<Vec<_>>();
+
+        let mut all = matches.clone();
+        sort_ranked_matches(&mut all, None);
+        assert_eq!(
+            all.iter().map(|m| m.order).collect::<Vec<_>>(),
+            vec![1, 3, 4, 0, 2]
+        );
+
+        let mut top = matches.clone();
+        sort_ranked_matches(&mut top, Some(3));
+        assert_eq!(
+            top.iter().map(|m| m.order).collect::<Vec<_>>(),
+            vec![1, 3, 4]
+        );
+
+        let mut none = matches;
+        sort_ranked_matches(&mut none, Some(0));
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn test_engine_ranking() {
+        let mut engine = DictionaryEngine::new(create_test_csv_data());
+
+        // An exact alias match outranks better scored canonical matches
+        let results = engine.fuzzy_search("girl", Some(5), None);
+        assert_eq!(results[0].term, "girl");
+        assert!(results.windows(2).all(|w| w[0].rank >= w[1].rank));
+
+        // Without popularity and bonuses, results are ordered by the nucleo score
+        engine.set_ranking_weights(RankingWeights {
+            popularity: 0.0,
+            exact_match_bonus: 0.0,
+            prefix_match_bonus: 0.0,
+            alias_penalty: 0.0,
+            ..Default::default()
+        });
+        let results = engine.fuzzy_search("hair", None, None);
+        assert!(results.windows(2).all(|w| w[0].score >= w[1].score));
+
+        // Popularity alone orders results by count
+        engine.set_ranking_weights(RankingWeights {
+            match_score: 0.0,
+            popularity: 1.0,
+            exact_match_bonus: 0.0,
+            prefix_match_bonus: 0.0,
+            alias_penalty: 0.0,
+            ..Default::default()
+        });
+        let results = engine.fuzzy_search("hair", None, None);
+        assert!(results.windows(2).all(|w| w[0].count >= w[1].count));
+    }
+
+    #[test]
+    fn test_priority_is_weighed_with_rank() {
+        let mut engine = DictionaryEngine::from_sources(
+            vec![
+                DictionarySourceInput {
+                    id: "base".to_string(),
+                    text: "long_hair,0,4181922,\nlong_hair_ribbon,0,100000,".to_string(),
+                    ..Default::default()
+                },
+                DictionarySourceInput {
+                    id: "custom".to_string(),
+                    priority: 1,
+                    text: "lxoxnxg_xhxaxixr,0,1,\nlong_hair_ornament,0,1,".to_string(),
+                    ..Default::default()
+                },
+            ],
+            None,
+        );
+        let results = engine.fuzzy_search("long_hair", None, None);
+        let terms = results.iter().map(|r| r.term.as_str()).collect::<Vec<_>>();
+
+        // A far better match outranks a poor one from a higher priority source,
+        // while priority still outweighs popularity among similar matches
+        assert_eq!(
+            terms[..3],
+            ["long_hair", "long_hair_ornament", "long_hair_ribbon"]
+        );
+        assert!(results.windows(2).all(|w| w[0].rank >= w[1].rank));
+    }
+}
@@ -0,0 +1,178 @@
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use super::{DictionaryEngine, DuplicateMergePolicy};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Tsify, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkipReason {
+    ParseError,
+    EmptyKey,
+    BadCount,
+}
+
+#[derive(Debug, Clone, PartialEq, Tsify, serde::Serialize, serde::Deserialize)]
+pub struct SkippedLine {
+    pub source: String,
+    // 1-based line number within the source, 0 if unknown
+    pub line: u64,
+    pub raw: String,
+    pub reason: SkipReason,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Tsify, serde::Serialize)]
+pub struct SourceLoadReport {
+    pub id: String,
+    pub total_lines: usize,
+    pub loaded_entries: usize,
+    pub skipped_lines: usize,
+}
+
+#[derive(Debug, Clone, Tsify, serde::Serialize)]
+#[tsify(into_wasm_abi)]
+pub struct LoadReport {
+    pub sources: Vec<SourceLoadReport>,
+    pub skipped_lines: Vec<SkippedLine>,
+    pub total_entries: usize,
+    pub aliases_indexed: usize,
+    pub merge_policy: DuplicateMergePolicy,
+    pub merged_duplicates: usize,
+}
+
+#[wasm_bindgen]
+impl DictionaryEngine {
+    /// Describes what was loaded from each source, including every line that was skipped and why.
+    /// Reflects the current state after any `add_source` or `remove_source` calls.
+    #[wasm_bindgen]
+    pub fn load_report(&self) -> LoadReport {
+        let live_entries = self.dictionary.iter().filter(|e| !e.shadowed);
+
+        LoadReport {
+            sources: self
+                .sources
+                .iter()
+                .map(|source| SourceLoadReport {
+                    id: source.id.clone(),
+                    total_lines: source.total_lines,
+                    loaded_entries: source.len,
+                    skipped_lines: source.skipped_lines.len(),
+                })
+                .collect(),
+            skipped_lines: self
+                .sources
+                .iter()
+                .flat_map(|source| source.skipped_lines.iter().cloned())
+                .collect(),
+            total_entries: live_entries.clone().count(),
+            aliases_indexed: live_entries.map(|e| e.aliases.len()).sum(),
+            merge_policy: self.options.merge_policy,
+            merged_duplicates: self.duplicates.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{DictionaryEngineOptions, DictionarySourceInput};
+    use super::*;
+
+    #[test]
+    fn test_load_report_counts() {
+        let engine = DictionaryEngine::new(vec![
+            "1girl,0,100,\"girl,女の子\"\nsolo,0,50,".to_string(),
+            "cat,7,10,\"kitten,neko,猫\"".to_string(),
+        ]);
+        let report = engine.load_report();
+
+        assert_eq!(report.sources.len(), 2);
+        assert_eq!(report.sources[0].id, "0");
+        assert_eq!(report.sources[0].total_lines, 2);
+        assert_eq!(report.sources[0].loaded_entries, 2);
+        assert_eq!(report.sources[1].total_lines, 1);
+        assert_eq!(report.total_entries, 3);
+        assert_eq!(report.aliases_indexed, 5);
+        assert!(report.skipped_lines.is_empty());
+        assert_eq!(report.merge_policy, DuplicateMergePolicy::KeepAll);
+        assert_eq!(report.merged_duplicates, 0);
+    }
+
+    #[test]
+    fn test_load_report_skipped_lines() {
+        let engine = DictionaryEngine::from_sources(
+            vec![DictionarySourceInput {
+                id: "custom".to_string(),
+                text: "valid,0,1000,alias\n,0,5,empty\nbroken,0,lots,x\nshort,0\nvalid2,0,5,"
+                    .to_string(),
+                ..Default::default()
+            }],
+            None,
+        );
+        let report = engine.load_report();
+
+        assert_eq!(report.sources[0].total_lines, 5);
+        assert_eq!(report.sources[0].loaded_entries, 2);
+        assert_eq!(report.sources[0].skipped_lines, 3);
+
+        let skipped = report
+            .skipped_lines
+            .iter()
+            .map(|s| (s.source.as_str(), s.line, s.raw.as_str(), s.reason))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            skipped,
+            vec![
+                ("custom", 2, ",0,5,empty", SkipReason::EmptyKey),
+                ("custom", 3, "broken,0,lots,x", SkipReason::BadCount),
+                ("custom", 4, "short,0", SkipReason::ParseError),
+            ]
+        );
+        assert!(!report.skipped_lines[2].message.is_empty());
+    }
+
+    #[test]
+    fn test_load_report_tracks_incremental_updates() {
+        let mut engine = DictionaryEngine::new(vec!["tag,0,1,alias".to_string()]);
+        engine.add_source("user".to_string(), "user_tag,0,1,\n,0,1,", None, None);
+
+        let report = engine.load_report();
+        assert_eq!(report.sources.len(), 2);
+        assert_eq!(report.sources[1].id, "user");
+        assert_eq!(report.skipped_lines.len(), 1);
+        assert_eq!(report.skipped_lines[0].source, "user");
+
+        engine.remove_source("user");
+        let report = engine.load_report();
+        assert_eq!(report.sources.len(), 1);
+        assert!(report.skipped_lines.is_empty());
+        assert_eq!(report.total_entries, 1);
+    }
+
+    #[test]
+    fn test_load_report_merge_policy() {
+        let engine = DictionaryEngine::from_sources(
+            vec![
+                DictionarySourceInput {
+                    id: "a".to_string(),
+                    text: "tag,0,1,x\nother,0,1,".to_string(),
+                    ..Default::default()
+                },
+                DictionarySourceInput {
+                    id: "b".to_string(),
+                    text: "tag,0,2,\"x,y\"".to_string(),
+                    ..Default::default()
+                },
+            ],
+            Some(DictionaryEngineOptions {
+                merge_policy: DuplicateMergePolicy::UnionAliases,
+                ..Default::default()
+            }),
+        );
+        let report = engine.load_report();
+
+        assert_eq!(report.merge_policy, DuplicateMergePolicy::UnionAliases);
+        assert_eq!(report.merged_duplicates, 1);
+        assert_eq!(report.total_entries, 2);
+        assert_eq!(report.aliases_indexed, 2);
+    }
+}
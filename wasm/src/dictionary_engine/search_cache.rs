@@ -0,0 +1,298 @@
+use std::cmp::Reverse;
+
+use nucleo_matcher::{Matcher, pattern::Pattern};
+
+use super::{Interruption, SearchBudget};
+
+// The keys matched by the last fuzzy search, reused when the next query extends it
+#[derive(Debug)]
+pub(super) struct SearchCache {
+    completion_query: String,
+    try_non_ascii: bool,
+    // Positions of the matched keys across both haystacks, ASCII first, ascending
+    positions: Vec<u32>,
+}
+
+impl SearchCache {
+    // Whether every key matching `completion_query` is among the cached ones. Appending to a
+    // pattern only narrows it, except within negated, escaped or suffix-anchored atoms.
+    fn covers(&self, completion_query: &str, try_non_ascii: bool) -> bool {
+        self.try_non_ascii == try_non_ascii
+            && completion_query.starts_with(&self.completion_query)
+            && !self.completion_query.contains(['!', '\\', '$'])
+    }
+}
+
+// A haystack key with its position across both haystacks
+struct HaystackKey<'a> {
+    position: usize,
+    key: &'a str,
+}
+
+impl AsRef<str> for HaystackKey<'_> {
+    fn as_ref(&self) -> &str {
+        self.key
+    }
+}
+
+// Keys matched between two budget checks. Haystacks are sorted by score, so a search cut short
+// by its deadline has matched the most popular keys. The budget is only checked between chunks,
+// so a deadline can be overrun by the time taken to match one chunk.
+const MATCH_CHUNK_SIZE: usize = 4096;
+
+// Matches the pattern against the haystacks, only re-scoring the keys matched last time when the
+// query extends the cached one. Results are identical to a full search, and the cache is replaced.
+// The budget is checked before each chunk of either haystack, including the first non-ASCII one.
+// Once it is exceeded, matching stops and the reason is returned; interrupted results are not cached.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn match_haystacks<'a>(
+    pattern: &Pattern,
+    completion_query: &str,
+    try_non_ascii: bool,
+    haystack_ascii: &'a [String],
+    haystack_non_ascii: &'a [String],
+    matcher: &mut Matcher,
+    cache: &mut Option<SearchCache>,
+    budget: &SearchBudget,
+) -> (Vec<(&'a str, u32)>, Option<Interruption>) {
+    let key_at = |position: u32| {
+        let position = position as usize;
+        let key = match haystack_ascii.get(position) {
+            Some(key) => key.as_str(),
+            None => haystack_non_ascii[position - haystack_ascii.len()].as_str(),
+        };
+        HaystackKey { position, key }
+    };
+
+    let cached = cache
+        .take()
+        .filter(|cache| cache.covers(completion_query, try_non_ascii));
+    let (ascii_len, len) = match &cached {
+        Some(cache) => (
+            cache
+                .positions
+                .partition_point(|&p| (p as usize) < haystack_ascii.len()),
+            cache.positions.len(),
+        ),
+        None if try_non_ascii => (
+            haystack_ascii.len(),
+            haystack_ascii.len() + haystack_non_ascii.len(),
+        ),
+        None => (haystack_ascii.len(), haystack_ascii.len()),
+    };
+    // Chunks do not straddle the haystacks, so the non-ASCII pass starts with a check
+    let chunks = (0..ascii_len)
+        .step_by(MATCH_CHUNK_SIZE)
+        .map(|start| start..(start + MATCH_CHUNK_SIZE).min(ascii_len))
+        .chain(
+            (ascii_len..len)
+                .step_by(MATCH_CHUNK_SIZE)
+                .map(|start| start..(start + MATCH_CHUNK_SIZE).min(len)),
+        );
+
+    let mut matches = Vec::new();
+    let mut interruption = None;
+    for (i, chunk) in chunks.enumerate() {
+        if i > 0 {
+            interruption = budget.check();
+            if interruption.is_some() {
+                break;
+            }
+        }
+        let (start, end) = (chunk.start, chunk.end);
+        let chunk = match &cached {
+            Some(cache) => pattern.match_list(
+                cache.positions[start..end].iter().map(|&p| key_at(p)),
+                matcher,
+            ),
+            None => pattern.match_list((start as u32..end as u32).map(key_at), matcher),
+        };
+        matches.extend(chunk);
+    }
+    // Stable, like the sort within each chunk, so the order matches a single pass
+    matches.sort_by_key(|(_, score)| Reverse(*score));
+
+    if interruption.is_none() {
+        let mut positions = matches
+            .iter()
+            .map(|(key, _)| key.position as u32)
+            .collect::<Vec<_>>();
+        positions.sort_unstable();
+        *cache = Some(SearchCache {
+            completion_query: completion_query.to_string(),
+            try_non_ascii,
+            positions,
+        });
+    }
+
+    let matches = matches
+        .into_iter()
+        .map(|(key, score)| (key.key, score))
+        .collect();
+    (matches, interruption)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::create_test_csv_data;
+    use super::super::{CompletionResultEntry, DictionaryEngine, GenerationSignal, Instant};
+    use super::*;
+    use nucleo_matcher::{
+        Config,
+        pattern::{CaseMatching, Normalization},
+    };
+
+    fn terms(results: Vec<CompletionResultEntry>) -> Vec<(String, u32)> {
+        results.into_iter().map(|r| (r.term, r.score)).collect()
+    }
+
+    #[test]
+    fn test_covers() {
+        let cache = SearchCache {
+            completion_query: "long ".to_string(),
+            try_non_ascii: false,
+            positions: Vec::new(),
+        };
+        assert!(cache.covers("long h", false));
+        assert!(cache.covers("long ", false));
+        assert!(!cache.covers("long h", true));
+        assert!(!cache.covers("lon", false));
+        assert!(!cache.covers("short", false));
+
+        let negated = SearchCache {
+            completion_query: "hair !b".to_string(),
+            try_non_ascii: false,
+            positions: Vec::new(),
+        };
+        assert!(!negated.covers("hair !bl", false));
+    }
+
+    #[test]
+    fn test_cached_search_matches_fresh_search() {
+        let mut engine = DictionaryEngine::new(create_test_csv_data());
+
+        let mut query = String::new();
+        for c in "long_hair".chars() {
+            query.push(c);
+            let cached = terms(engine.fuzzy_search(&query, None, None));
+            let mut fresh_engine = DictionaryEngine::new(create_test_csv_data());
+            let fresh = terms(fresh_engine.fuzzy_search(&query, None, None));
+            assert_eq!(cached, fresh, "query: {query}");
+        }
+        assert_eq!(
+            engine.search_cache.as_ref().unwrap().completion_query,
+            "long hair"
+        );
+
+        // Changing direction falls back to a full search
+        let results = terms(engine.fuzzy_search("smile", None, None));
+        assert!(results.iter().any(|(term, _)| term == "smile"));
+    }
+
+    #[test]
+    fn test_budget_interrupts_matching() {
+        let haystack = (0..MATCH_CHUNK_SIZE * 3)
+            .map(|i| format!("tag {i}"))
+            .collect::<Vec<_>>();
+        let pattern = Pattern::parse("tag", CaseMatching::Smart, Normalization::Smart);
+        let mut matcher = Matcher::new(Config::DEFAULT);
+        let mut cache = None;
+        let mut match_with = |budget: &SearchBudget, cache: &mut Option<SearchCache>| {
+            match_haystacks(
+                &pattern,
+                "tag",
+                false,
+                &haystack,
+                &[],
+                &mut matcher,
+                cache,
+                budget,
+            )
+        };
+
+        let (matches, interruption) = match_with(
+            &SearchBudget {
+                deadline: Some(Instant::now()),
+                ..Default::default()
+            },
+            &mut cache,
+        );
+        assert_eq!(interruption, Some(Interruption::Deadline));
+        assert_eq!(matches.len(), MATCH_CHUNK_SIZE);
+        assert_eq!(matches[0].0, "tag 0");
+        assert!(cache.is_none());
+
+        let signal: GenerationSignal = Box::new(|| 2);
+        let (_, interruption) = match_with(
+            &SearchBudget {
+                generation: Some(1),
+                signal: Some(&signal),
+                ..Default::default()
+            },
+            &mut cache,
+        );
+        assert_eq!(interruption, Some(Interruption::Superseded));
+        assert!(cache.is_none());
+
+        let (matches, interruption) = match_with(
+            &SearchBudget {
+                generation: Some(2),
+                signal: Some(&signal),
+                ..Default::default()
+            },
+            &mut cache,
+        );
+        assert_eq!(interruption, None);
+        assert_eq!(matches.len(), haystack.len());
+        assert!(cache.is_some());
+    }
+
+    #[test]
+    fn test_budget_checked_before_non_ascii_pass() {
+        let ascii = vec!["tag a".to_string(), "tag b".to_string()];
+        let non_ascii = vec!["tag あ".to_string()];
+        let pattern = Pattern::parse("tag", CaseMatching::Smart, Normalization::Smart);
+        let mut matcher = Matcher::new(Config::DEFAULT);
+        let mut match_with = |budget: &SearchBudget, cache: &mut Option<SearchCache>| {
+            match_haystacks(
+                &pattern,
+                "tag",
+                true,
+                &ascii,
+                &non_ascii,
+                &mut matcher,
+                cache,
+                budget,
+            )
+        };
+
+        // The whole ASCII haystack fits in one chunk, yet the non-ASCII one is not matched
+        let past = SearchBudget {
+            deadline: Some(Instant::now()),
+            ..Default::default()
+        };
+        let (matches, interruption) = match_with(&past, &mut None);
+        assert_eq!(interruption, Some(Interruption::Deadline));
+        assert_eq!(matches.len(), 2);
+
+        // Cached positions are split the same way
+        let mut cache = None;
+        let (matches, _) = match_with(&SearchBudget::default(), &mut cache);
+        assert_eq!(matches.len(), 3);
+        let (matches, interruption) = match_with(&past, &mut cache);
+        assert_eq!(interruption, Some(Interruption::Deadline));
+        assert_eq!(matches.len(), 2);
+    }
+
+    #[test]
+    fn test_cache_invalidated_on_dictionary_change() {
+        let mut engine = DictionaryEngine::new(create_test_csv_data());
+        engine.fuzzy_search("long", None, None);
+        assert!(engine.search_cache.is_some());
+
+        engine.add_source("extra".to_string(), "long_coat,0,10,", None, None);
+        assert!(engine.search_cache.is_none());
+        let results = engine.fuzzy_search("long_c", None, None);
+        assert!(results.iter().any(|r| r.term == "long_coat"));
+    }
+}
@@ -0,0 +1,378 @@
+use std::collections::{HashMap, HashSet};
+
+use wasm_bindgen::prelude::*;
+
+use super::{
+    DictionaryEngine, DictionaryEngineOptions, DictionaryEntry, DictionarySource, DuplicateGroup,
+    IndexEntry, Instant, log_performance,
+};
+
+// Snapshot layout: magic bytes, then a postcard-encoded header, then a postcard-encoded payload
+const SNAPSHOT_MAGIC: &[u8; 4] = b"CPSD";
+// Bump this whenever the payload layout changes
+const SNAPSHOT_FORMAT_VERSION: u32 = 14;
+const CRATE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SnapshotHeader {
+    format_version: u32,
+    crate_version: String,
+}
+
+#[derive(serde::Serialize)]
+struct SnapshotPayloadRef<'a> {
+    dictionary: &'a [DictionaryEntry],
+    sources: &'a [DictionarySource],
+    duplicates: &'a HashMap<String, DuplicateGroup>,
+    options: &'a DictionaryEngineOptions,
+    completion_haystack_ascii: &'a [String],
+    completion_haystack_non_ascii: &'a [String],
+    completion_key_index: &'a [String],
+    completion_map: &'a HashMap<String, Vec<IndexEntry>>,
+    query_map: &'a HashMap<String, Vec<IndexEntry>>,
+}
+
+#[derive(serde::Deserialize)]
+struct SnapshotPayload {
+    dictionary: Vec<DictionaryEntry>,
+    sources: Vec<DictionarySource>,
+    duplicates: HashMap<String, DuplicateGroup>,
+    options: DictionaryEngineOptions,
+    completion_haystack_ascii: Vec<String>,
+    completion_haystack_non_ascii: Vec<String>,
+    completion_key_index: Vec<String>,
+    completion_map: HashMap<String, Vec<IndexEntry>>,
+    query_map: HashMap<String, Vec<IndexEntry>>,
+}
+
+impl SnapshotPayload {
+    // Checks that every index points into the dictionary and that the haystacks hold exactly the
+    // completion keys, so that a corrupted snapshot is rejected instead of panicking in a search
+    fn validate(&self) -> Result<(), String> {
+        let len = self.dictionary.len();
+        let is_valid = |&IndexEntry { index, alias_index }: &IndexEntry| {
+            self.dictionary
+                .get(index)
+                .is_some_and(|entry| alias_index.is_none_or(|i| i < entry.aliases.len()))
+        };
+
+        if self.sources.iter().map(|source| source.len).sum::<usize>() != len {
+            return Err("Invalid snapshot: source lengths do not match the dictionary".to_string());
+        }
+        for (name, map) in [
+            ("completion", &self.completion_map),
+            ("query", &self.query_map),
+        ] {
+            if let Some(key) = map
+                .iter()
+                .find_map(|(key, indices)| (!indices.iter().all(is_valid)).then_some(key))
+            {
+                return Err(format!(
+                    "Invalid snapshot: {name} key '{key}' points outside the dictionary"
+                ));
+            }
+        }
+        if let Some(group) = self.duplicates.values().find(|group| {
+            group.members.is_empty() || group.members.iter().any(|&member| member >= len)
+        }) {
+            return Err(format!(
+                "Invalid snapshot: duplicate group of '{}' points outside the dictionary",
+                group.primary.key
+            ));
+        }
+
+        // The haystacks must hold every completion key once
+        let mut haystack_keys = HashSet::with_capacity(self.completion_map.len());
+        for key in self
+            .completion_haystack_ascii
+            .iter()
+            .chain(&self.completion_haystack_non_ascii)
+        {
+            if !self.completion_map.contains_key(key) {
+                return Err(format!(
+                    "Invalid snapshot: haystack key '{key}' has no completion entries"
+                ));
+            }
+            if !haystack_keys.insert(key.as_str()) {
+                return Err(format!(
+                    "Invalid snapshot: haystack key '{key}' appears more than once"
+                ));
+            }
+        }
+        if haystack_keys.len() != self.completion_map.len() {
+            return Err("Invalid snapshot: haystacks do not match the completion keys".to_string());
+        }
+        // The index must hold every completion key once, in order
+        if self.completion_key_index.len() != self.completion_map.len()
+            || self.completion_key_index.windows(2).any(|w| w[0] >= w[1])
+            || !self
+                .completion_key_index
+                .iter()
+                .all(|key| self.completion_map.contains_key(key))
+        {
+            return Err(
+                "Invalid snapshot: key index does not match the completion keys".to_string(),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+impl DictionaryEngine {
+    /// Serializes the fully built engine (dictionary, maps, sorted haystacks and key index) into a binary snapshot
+    /// that can be restored with `from_snapshot` without parsing or sorting.
+    #[wasm_bindgen]
+    pub fn to_snapshot(&self) -> Result<Vec<u8>, String> {
+        let start_time = Instant::now();
+
+        let header = SnapshotHeader {
+            format_version: SNAPSHOT_FORMAT_VERSION,
+            crate_version: CRATE_VERSION.to_string(),
+        };
+        let payload = SnapshotPayloadRef {
+            dictionary: &self.dictionary,
+            sources: &self.sources,
+            duplicates: &self.duplicates,
+            options: &self.options,
+            completion_haystack_ascii: &self.completion_haystack_ascii,
+            completion_haystack_non_ascii: &self.completion_haystack_non_ascii,
+            completion_key_index: &self.completion_key_index,
+            completion_map: &self.completion_map,
+            query_map: &self.query_map,
+        };
+
+        let mut buffer = SNAPSHOT_MAGIC.to_vec();
+        buffer = postcard::to_extend(&header, buffer)
+            .map_err(|err| format!("Failed to serialize snapshot header: {err}"))?;
+        buffer = postcard::to_extend(&payload, buffer)
+            .map_err(|err| format!("Failed to serialize snapshot: {err}"))?;
+
+        log_performance(
+            "Snapshot serialization",
+            start_time.elapsed(),
+            Some(&format!("{} bytes", buffer.len())),
+        );
+
+        Ok(buffer)
+    }
+
+    /// Restores an engine from a snapshot produced by `to_snapshot`.
+    /// Snapshots written by a different format or crate version, or with inconsistent indices, are rejected.
+    #[wasm_bindgen]
+    pub fn from_snapshot(snapshot: &[u8]) -> Result<DictionaryEngine, String> {
+        let start_time = Instant::now();
+
+        let body = snapshot
+            .strip_prefix(SNAPSHOT_MAGIC.as_slice())
+            .ok_or_else(|| "Invalid snapshot format".to_string())?;
+
+        let (header, body) = postcard::take_from_bytes::<SnapshotHeader>(body)
+            .map_err(|err| format!("Failed to read snapshot header: {err}"))?;
+        if header.format_version != SNAPSHOT_FORMAT_VERSION || header.crate_version != CRATE_VERSION
+        {
+            return Err(format!(
+                "Incompatible snapshot: created by cps-lib {} (format {}), expected cps-lib {} (format {})",
+                header.crate_version, header.format_version, CRATE_VERSION, SNAPSHOT_FORMAT_VERSION
+            ));
+        }
+
+        let payload: SnapshotPayload = postcard::from_bytes(body)
+            .map_err(|err| format!("Failed to deserialize snapshot: {err}"))?;
+        payload.validate()?;
+
+        log_performance(
+            "Snapshot deserialization",
+            start_time.elapsed(),
+            Some(&format!("Total entries: {}", payload.dictionary.len())),
+        );
+
+        Ok(DictionaryEngine {
+            dictionary: payload.dictionary,
+            sources: payload.sources,
+            duplicates: payload.duplicates,
+            options: payload.options,
+            completion_haystack_ascii: payload.completion_haystack_ascii,
+            completion_haystack_non_ascii: payload.completion_haystack_non_ascii,
+            completion_key_index: payload.completion_key_index,
+            completion_map: payload.completion_map,
+            query_map: payload.query_map,
+            nucleo_matcher: Self::create_nucleo_matcher(),
+            search_cache: None,
+            search_generation: 0,
+            generation_signal: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::create_test_csv_data;
+    use super::*;
+
+    fn encode_header(header: &SnapshotHeader) -> Vec<u8> {
+        postcard::to_extend(header, SNAPSHOT_MAGIC.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut engine = DictionaryEngine::new(create_test_csv_data());
+        let snapshot = engine.to_snapshot().unwrap();
+        assert!(snapshot.starts_with(SNAPSHOT_MAGIC));
+
+        let mut restored = DictionaryEngine::from_snapshot(&snapshot).unwrap();
+        assert_eq!(restored.dictionary.len(), engine.dictionary.len());
+        assert_eq!(
+            restored.completion_haystack_ascii,
+            engine.completion_haystack_ascii
+        );
+        assert_eq!(
+            restored.completion_haystack_non_ascii,
+            engine.completion_haystack_non_ascii
+        );
+        assert_eq!(restored.completion_key_index, engine.completion_key_index);
+        assert_eq!(restored.completion_map, engine.completion_map);
+        assert_eq!(restored.query_map, engine.query_map);
+
+        // Searches should behave identically
+        for query in ["girl", "hair", "金髪", "smiling"] {
+            let expected = engine.fuzzy_search(query, Some(10), Some(true));
+            let actual = restored.fuzzy_search(query, Some(10), Some(true));
+            let expected = expected
+                .iter()
+                .map(|r| (&r.term, &r.canonical_key, r.score))
+                .collect::<Vec<_>>();
+            let actual = actual
+                .iter()
+                .map(|r| (&r.term, &r.canonical_key, r.score))
+                .collect::<Vec<_>>();
+            assert_eq!(actual, expected, "Mismatch for query: {query}");
+        }
+
+        let results = restored.query_words(vec!["金髪ロング".to_string()]);
+        assert_eq!(results[0].1.len(), 2);
+    }
+
+    #[test]
+    fn test_snapshot_empty_engine() {
+        let engine = DictionaryEngine::new(vec![]);
+        let snapshot = engine.to_snapshot().unwrap();
+        let restored = DictionaryEngine::from_snapshot(&snapshot).unwrap();
+        assert!(restored.dictionary.is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_invalid_magic() {
+        let result = DictionaryEngine::from_snapshot(b"not a snapshot");
+        assert_eq!(result.err().unwrap(), "Invalid snapshot format");
+    }
+
+    #[test]
+    fn test_snapshot_incompatible_crate_version() {
+        let snapshot = encode_header(&SnapshotHeader {
+            format_version: SNAPSHOT_FORMAT_VERSION,
+            crate_version: "0.0.0-other".to_string(),
+        });
+
+        let error = DictionaryEngine::from_snapshot(&snapshot).err().unwrap();
+        assert!(error.starts_with("Incompatible snapshot"));
+        assert!(error.contains("0.0.0-other"));
+        assert!(error.contains(CRATE_VERSION));
+    }
+
+    #[test]
+    fn test_snapshot_incompatible_format_version() {
+        let snapshot = encode_header(&SnapshotHeader {
+            format_version: SNAPSHOT_FORMAT_VERSION + 1,
+            crate_version: CRATE_VERSION.to_string(),
+        });
+
+        let error = DictionaryEngine::from_snapshot(&snapshot).err().unwrap();
+        assert!(error.starts_with("Incompatible snapshot"));
+    }
+
+    #[test]
+    fn test_snapshot_truncated_payload() {
+        let engine = DictionaryEngine::new(create_test_csv_data());
+        let snapshot = engine.to_snapshot().unwrap();
+
+        let error = DictionaryEngine::from_snapshot(&snapshot[..snapshot.len() / 2])
+            .err()
+            .unwrap();
+        assert!(error.starts_with("Failed to deserialize snapshot"));
+    }
+
+    #[test]
+    fn test_snapshot_invalid_indices() {
+        let engine = DictionaryEngine::new(create_test_csv_data());
+        let encode = |payload: &SnapshotPayloadRef| {
+            let header = SnapshotHeader {
+                format_version: SNAPSHOT_FORMAT_VERSION,
+                crate_version: CRATE_VERSION.to_string(),
+            };
+            postcard::to_extend(payload, encode_header(&header)).unwrap()
+        };
+        let payload = |dictionary, query_map| SnapshotPayloadRef {
+            dictionary,
+            sources: &engine.sources,
+            duplicates: &engine.duplicates,
+            options: &engine.options,
+            completion_haystack_ascii: &engine.completion_haystack_ascii,
+            completion_haystack_non_ascii: &engine.completion_haystack_non_ascii,
+            completion_key_index: &engine.completion_key_index,
+            completion_map: &engine.completion_map,
+            query_map,
+        };
+
+        // A dictionary cut short leaves map entries pointing past its end
+        let truncated = &engine.dictionary[..engine.dictionary.len() - 1];
+        let error =
+            DictionaryEngine::from_snapshot(&encode(&payload(truncated, &engine.query_map)))
+                .err()
+                .unwrap();
+        assert!(error.starts_with("Invalid snapshot"));
+
+        let mut query_map = engine.query_map.clone();
+        query_map.insert(
+            "broken".to_string(),
+            vec![IndexEntry {
+                index: 0,
+                alias_index: Some(100),
+            }],
+        );
+        let error =
+            DictionaryEngine::from_snapshot(&encode(&payload(&engine.dictionary, &query_map)))
+                .err()
+                .unwrap();
+        assert!(error.contains("'broken'"));
+
+        let mut completion_map = engine.completion_map.clone();
+        completion_map.remove(&engine.completion_haystack_ascii[0]);
+        let snapshot = encode(&SnapshotPayloadRef {
+            completion_map: &completion_map,
+            ..payload(&engine.dictionary, &engine.query_map)
+        });
+        let error = DictionaryEngine::from_snapshot(&snapshot).err().unwrap();
+        assert!(error.contains("has no completion entries"));
+
+        // A duplicated key cannot stand in for a missing one
+        let mut haystack = engine.completion_haystack_ascii.clone();
+        haystack[1] = haystack[0].clone();
+        let snapshot = encode(&SnapshotPayloadRef {
+            completion_haystack_ascii: &haystack,
+            ..payload(&engine.dictionary, &engine.query_map)
+        });
+        let error = DictionaryEngine::from_snapshot(&snapshot).err().unwrap();
+        assert!(error.contains("appears more than once"));
+
+        let mut key_index = engine.completion_key_index.clone();
+        key_index.swap(0, 1);
+        let snapshot = encode(&SnapshotPayloadRef {
+            completion_key_index: &key_index,
+            ..payload(&engine.dictionary, &engine.query_map)
+        });
+        let error = DictionaryEngine::from_snapshot(&snapshot).err().unwrap();
+        assert!(error.contains("key index"));
+    }
+}
@@ -0,0 +1,229 @@
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use super::typo::{max_typo_distance, search_key_index};
+use super::{DictionaryEngine, IndexEntry, Instant, QueryResultEntryValue, log_performance};
+use crate::normalize::normalize_for_auto_completion;
+
+const DEFAULT_MAX_SUGGESTIONS: usize = 3;
+
+// A known term close to a word without entries
+#[derive(Debug, Clone, Tsify, serde::Serialize)]
+#[tsify(into_wasm_abi)]
+pub struct QuerySuggestion {
+    pub term: String,
+    pub canonical_key: String,
+    pub is_canonical: bool,
+    pub category: i32,
+    pub count: i32,
+    // Edits between the word and the term, after normalization for auto completion
+    pub distance: u32,
+}
+
+// Like `QueryResultEntry`, with near misses for words without entries
+#[derive(Debug, Tsify, serde::Serialize)]
+#[tsify(into_wasm_abi)]
+pub struct SuggestedQueryResultEntry(
+    pub String,
+    pub Vec<QueryResultEntryValue>,
+    pub Vec<QuerySuggestion>,
+);
+
+impl DictionaryEngine {
+    // The completion key of the term an index entry refers to
+    fn term_key(&self, &IndexEntry { index, alias_index }: &IndexEntry) -> String {
+        let entry = &self.dictionary[index];
+        let term = match alias_index {
+            Some(alias_index) => &entry.aliases[alias_index],
+            None => &entry.key,
+        };
+        normalize_for_auto_completion(term)
+    }
+
+    // Terms within the typo distance of the word, closest first, then most popular. Candidates
+    // come from the completion key index, like typo corrections of fuzzy search.
+    fn suggest(&self, word: &str, max_suggestions: usize) -> Vec<QuerySuggestion> {
+        let word = normalize_for_auto_completion(word.trim());
+        let word = word.chars().collect::<Vec<_>>();
+        let max = max_typo_distance(word.len());
+        if max == 0 || max_suggestions == 0 {
+            return Vec::new();
+        }
+
+        let mut candidates = Vec::new();
+        for (key, distance) in search_key_index(&self.completion_key_index, &word, max, false) {
+            let indices = self.completion_map.get(key).into_iter().flatten();
+            // Secondary keys like romaji are not spellings of the term
+            candidates.extend(
+                indices
+                    .filter(|entry| self.term_key(entry) == key)
+                    .map(|&entry| (distance, entry)),
+            );
+        }
+
+        candidates.sort_by(|(a_distance, a), (b_distance, b)| {
+            let (a_entry, b_entry) = (&self.dictionary[a.index], &self.dictionary[b.index]);
+            a_distance
+                .cmp(b_distance)
+                .then_with(|| b_entry.priority.cmp(&a_entry.priority))
+                .then_with(|| b_entry.count.cmp(&a_entry.count))
+                .then_with(|| a.alias_index.is_some().cmp(&b.alias_index.is_some()))
+                .then_with(|| (a.index, a.alias_index).cmp(&(b.index, b.alias_index)))
+        });
+
+        let mut suggestions: Vec<QuerySuggestion> = Vec::new();
+        for (distance, IndexEntry { index, alias_index }) in candidates {
+            let entry = &self.dictionary[index];
+            let (term, is_canonical) = match alias_index {
+                Some(alias_index) => (&entry.aliases[alias_index], false),
+                None => (&entry.key, true),
+            };
+            // The same term from another source would be the same fix
+            if suggestions
+                .iter()
+                .any(|s| s.term == *term && s.canonical_key == entry.key)
+            {
+                continue;
+            }
+            suggestions.push(QuerySuggestion {
+                term: term.clone(),
+                canonical_key: entry.key.clone(),
+                is_canonical,
+                category: entry.category,
+                count: entry.count,
+                distance: distance as u32,
+            });
+            if suggestions.len() == max_suggestions {
+                break;
+            }
+        }
+        suggestions
+    }
+}
+
+#[wasm_bindgen]
+impl DictionaryEngine {
+    /// Like `query_words`, also suggesting up to `max_suggestions` (3 by default) close terms for each word
+    /// without entries, ranked by edit distance and then by count.
+    #[wasm_bindgen]
+    pub fn query_words_with_suggestions(
+        &self,
+        words: Vec<String>,
+        max_suggestions: Option<usize>,
+    ) -> Vec<SuggestedQueryResultEntry> {
+        let start_time = Instant::now();
+        let max_suggestions = max_suggestions.unwrap_or(DEFAULT_MAX_SUGGESTIONS);
+
+        let result = words
+            .into_iter()
+            .map(|word| {
+                let entries = self.lookup_word(&word);
+                let suggestions = if entries.is_empty() {
+                    self.suggest(&word, max_suggestions)
+                } else {
+                    Vec::new()
+                };
+                SuggestedQueryResultEntry(word, entries, suggestions)
+            })
+            .collect::<Vec<_>>();
+
+        log_performance(
+            "query_words_with_suggestions total",
+            start_time.elapsed(),
+            Some(&format!(
+                "words: {}, unknown: {}",
+                result.len(),
+                result
+                    .iter()
+                    .filter(|SuggestedQueryResultEntry(_, entries, _)| entries.is_empty())
+                    .count()
+            )),
+        );
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::create_test_csv_data;
+    use super::super::{DictionaryEngineOptions, DictionarySourceInput, SecondaryKeyOptions};
+    use super::*;
+
+    #[test]
+    fn test_query_words_with_suggestions() {
+        let engine = DictionaryEngine::new(create_test_csv_data());
+        let results = engine.query_words_with_suggestions(
+            vec![
+                "long_hiar".to_string(),
+                "smile".to_string(),
+                "smiel".to_string(),
+                "xyzzy".to_string(),
+            ],
+            None,
+        );
+
+        let SuggestedQueryResultEntry(word, entries, suggestions) = &results[0];
+        assert_eq!(word, "long_hiar");
+        assert!(entries.is_empty());
+        assert_eq!(suggestions[0].term, "long_hair");
+        assert_eq!(suggestions[0].distance, 1);
+        assert!(suggestions.len() <= DEFAULT_MAX_SUGGESTIONS);
+
+        // Known words get no suggestions
+        let SuggestedQueryResultEntry(_, entries, suggestions) = &results[1];
+        assert!(!entries.is_empty());
+        assert!(suggestions.is_empty());
+
+        let SuggestedQueryResultEntry(_, _, suggestions) = &results[2];
+        assert_eq!(suggestions[0].canonical_key, "smile");
+        assert!(
+            suggestions
+                .windows(2)
+                .all(|w| w[0].distance <= w[1].distance)
+        );
+
+        assert!(results[3].2.is_empty());
+    }
+
+    #[test]
+    fn test_max_suggestions() {
+        let engine = DictionaryEngine::new(create_test_csv_data());
+        let results = engine.query_words_with_suggestions(vec!["smiel".to_string()], Some(1));
+        assert_eq!(results[0].2.len(), 1);
+        let results = engine.query_words_with_suggestions(vec!["smiel".to_string()], Some(0));
+        assert!(results[0].2.is_empty());
+    }
+
+    #[test]
+    fn test_suggestions_for_aliases_only() {
+        let engine = DictionaryEngine::from_sources(
+            vec![DictionarySourceInput {
+                id: "base".to_string(),
+                text: "long_hair,0,5000,ロングヘア".to_string(),
+                ..Default::default()
+            }],
+            Some(DictionaryEngineOptions {
+                secondary_keys: SecondaryKeyOptions {
+                    romaji: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+        );
+        let results = engine.query_words_with_suggestions(
+            vec!["ロンゲヘア".to_string(), "ronguhae".to_string()],
+            None,
+        );
+
+        let SuggestedQueryResultEntry(_, _, suggestions) = &results[0];
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].term, "ロングヘア");
+        assert_eq!(suggestions[0].canonical_key, "long_hair");
+        assert!(!suggestions[0].is_canonical);
+
+        // Romaji keys are not spellings of the term
+        assert!(engine.completion_map.contains_key("ronguhea"));
+        assert!(results[1].2.is_empty());
+    }
+}
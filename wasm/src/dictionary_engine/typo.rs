@@ -0,0 +1,373 @@
+use std::collections::HashSet;
+
+use super::DictionaryEngine;
+
+// Typo-tolerant matching is tried when fewer primary matches than this are found
+pub(super) const DEFAULT_TYPO_FALLBACK_THRESHOLD: usize = 5;
+
+// Edits allowed for a query of the given length, none for queries too short to tell a typo from another tag
+pub(super) fn max_typo_distance(query_len: usize) -> usize {
+    match query_len {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    }
+}
+
+// Rows of the distance matrix between the query and the prefixes of the current key. Consecutive
+// keys of the sorted key index share the rows of their common prefix.
+struct TrieWalk<'q> {
+    query: &'q [char],
+    // The chars of the current prefix
+    path: Vec<char>,
+    // For the empty prefix and each char of the path, the row of the matrix and the smallest
+    // distance to the whole query so far
+    rows: Vec<Vec<usize>>,
+    best: Vec<usize>,
+}
+
+impl<'q> TrieWalk<'q> {
+    fn new(query: &'q [char]) -> Self {
+        TrieWalk {
+            query,
+            path: Vec::new(),
+            rows: vec![(0..=query.len()).collect()],
+            best: vec![query.len()],
+        }
+    }
+
+    fn truncate(&mut self, depth: usize) {
+        self.path.truncate(depth);
+        self.rows.truncate(depth + 1);
+        self.best.truncate(depth + 1);
+    }
+
+    // Appends a char to the current prefix, returning the smallest distance in its row
+    fn push(&mut self, k: char) -> usize {
+        let eq = |a: char, b: char| a.eq_ignore_ascii_case(&b);
+        let i = self.path.len();
+        let previous = &self.rows[i];
+        let mut current = vec![i + 1; self.query.len() + 1];
+        for (j, &q) in self.query.iter().enumerate() {
+            let cost = usize::from(!eq(k, q));
+            let mut distance = (previous[j] + cost)
+                .min(previous[j + 1] + 1)
+                .min(current[j] + 1);
+            if i > 0 && j > 0 && eq(k, self.query[j - 1]) && eq(self.path[i - 1], q) {
+                distance = distance.min(self.rows[i - 1][j - 1] + 1);
+            }
+            current[j + 1] = distance;
+        }
+
+        let min = current.iter().copied().min().unwrap_or_default();
+        self.best.push(self.best[i].min(current[self.query.len()]));
+        self.rows.push(current);
+        self.path.push(k);
+        min
+    }
+}
+
+// Keys of the sorted key index within `max` edits of the query, ignoring ASCII case, in index order.
+// The index is walked as a trie: keys sharing a prefix share the rows computed for it, and once
+// every distance in a row exceeds `max`, all keys starting with that prefix are settled at once.
+// With `prefix` set, keys are measured by their closest prefix, so that a partially typed tag with
+// a typo like `blonf` finds `blonde hair`.
+pub(super) fn search_key_index<'a>(
+    index: &'a [String],
+    query: &[char],
+    max: usize,
+    prefix: bool,
+) -> Vec<(&'a str, usize)> {
+    let mut walk = TrieWalk::new(query);
+    let mut found = Vec::new();
+
+    let mut position = 0;
+    'keys: while position < index.len() {
+        let key = index[position].as_str();
+        let common = walk
+            .path
+            .iter()
+            .zip(key.chars())
+            .take_while(|(a, b)| **a == *b)
+            .count();
+        walk.truncate(common);
+
+        for (offset, k) in key.char_indices().skip(common) {
+            if walk.push(k) <= max {
+                continue;
+            }
+            // No key starting with this prefix gets any closer
+            let subtree = &key[..offset + k.len_utf8()];
+            let end =
+                position + index[position..].partition_point(|other| other.starts_with(subtree));
+            let best = walk.best[walk.path.len()];
+            if prefix && best <= max {
+                found.extend(index[position..end].iter().map(|key| (key.as_str(), best)));
+            }
+            position = end;
+            continue 'keys;
+        }
+
+        let distance = if prefix {
+            walk.best[walk.path.len()]
+        } else {
+            walk.rows[walk.path.len()][query.len()]
+        };
+        if distance <= max {
+            found.push((key, distance));
+        }
+        position += 1;
+    }
+    found
+}
+
+impl DictionaryEngine {
+    // Completion keys within the typo distance of an ASCII query, excluding the keys already matched,
+    // with their distance. Only ASCII keys are considered, like in fuzzy matching of ASCII queries.
+    pub(super) fn typo_candidates(
+        &self,
+        completion_query: &str,
+        matched: &HashSet<&str>,
+    ) -> Vec<(&str, usize)> {
+        let query = completion_query.trim();
+        let max = max_typo_distance(query.len());
+        if max == 0 || !query.is_ascii() {
+            return Vec::new();
+        }
+
+        let query = query.chars().collect::<Vec<_>>();
+        search_key_index(&self.completion_key_index, &query, max, true)
+            .into_iter()
+            .filter(|(key, _)| key.is_ascii() && !matched.contains(key))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::create_test_csv_data;
+    use super::super::{DictionaryEngine, DictionarySourceInput, FuzzySearchOptions};
+    use super::*;
+
+    // Reference for the walk: the optimal string alignment distance between the query and the key,
+    // or the closest prefix of the key if `prefix` is set. `None` if it exceeds `max`.
+    fn osa_distance<T: Copy>(
+        query: &[T],
+        key: &[T],
+        max: usize,
+        prefix: bool,
+        eq: impl Fn(T, T) -> bool,
+    ) -> Option<usize> {
+        let key = if prefix {
+            // Prefixes longer than this are more than `max` edits away
+            &key[..key.len().min(query.len() + max)]
+        } else if key.len().abs_diff(query.len()) > max {
+            return None;
+        } else {
+            key
+        };
+
+        // Rows are key prefixes, columns are query prefixes
+        let mut before_previous = vec![0; query.len() + 1];
+        let mut previous = (0..=query.len()).collect::<Vec<_>>();
+        let mut current = vec![0; query.len() + 1];
+        let mut best = previous[query.len()];
+
+        for (i, &k) in key.iter().enumerate() {
+            current[0] = i + 1;
+            for (j, &q) in query.iter().enumerate() {
+                let cost = usize::from(!eq(k, q));
+                let mut distance = (previous[j] + cost)
+                    .min(previous[j + 1] + 1)
+                    .min(current[j] + 1);
+                if i > 0 && j > 0 && eq(k, query[j - 1]) && eq(key[i - 1], q) {
+                    distance = distance.min(before_previous[j - 1] + 1);
+                }
+                current[j + 1] = distance;
+            }
+            best = best.min(current[query.len()]);
+            // Every later row is at least as far away
+            if current.iter().all(|&distance| distance > max) {
+                return (prefix && best <= max).then_some(best);
+            }
+            std::mem::swap(&mut before_previous, &mut previous);
+            std::mem::swap(&mut previous, &mut current);
+        }
+
+        let distance = if prefix { best } else { previous[query.len()] };
+        (distance <= max).then_some(distance)
+    }
+
+    fn search<'a>(
+        keys: &[&'a str],
+        query: &str,
+        max: usize,
+        prefix: bool,
+    ) -> Vec<(&'a str, usize)> {
+        let mut index = keys.iter().map(|key| key.to_string()).collect::<Vec<_>>();
+        index.sort_unstable();
+        let query = query.chars().collect::<Vec<_>>();
+        search_key_index(&index, &query, max, prefix)
+            .into_iter()
+            .map(|(key, distance)| (*keys.iter().find(|k| **k == key).unwrap(), distance))
+            .collect()
+    }
+
+    #[test]
+    fn test_search_key_index_by_prefix() {
+        const KEYS: [&str; 6] = [
+            "long hair",
+            "long",
+            "blonde hair",
+            "blue eyes",
+            "Blonde",
+            "lo",
+        ];
+        let found = |query, max| search(&KEYS, query, max, true);
+        assert_eq!(found("long hair", 2), vec![("long hair", 0)]);
+        assert_eq!(found("lnog hair", 2), vec![("long hair", 1)]);
+        assert_eq!(found("blonf", 1), vec![("Blonde", 1), ("blonde hair", 1)]);
+        assert_eq!(found("blnde", 1), vec![("Blonde", 1), ("blonde hair", 1)]);
+        assert_eq!(found("long", 1), vec![("long", 0), ("long hair", 0)]);
+        assert_eq!(found("xyz", 2), vec![]);
+        assert_eq!(found("lnog har", 1), vec![]);
+        assert_eq!(found("lnog har", 2), vec![("long hair", 2)]);
+    }
+
+    #[test]
+    fn test_search_key_index_matches_scan() {
+        let keys = [
+            "long hair",
+            "long",
+            "longer",
+            "lo",
+            "very long hair",
+            "blonde hair",
+            "blonde",
+            "Blond",
+            "blue eyes",
+            "blush",
+            "smile",
+            "smiling",
+            "smirk",
+            "ロングヘア",
+            "ロング",
+        ];
+        let mut index = keys.iter().map(|key| key.to_string()).collect::<Vec<_>>();
+        index.sort_unstable();
+        for query in [
+            "lnog",
+            "blonf",
+            "smiel",
+            "bleu eye",
+            "ロンゲ",
+            "lo",
+            "hair",
+            "sm",
+        ] {
+            let query = query.chars().collect::<Vec<_>>();
+            for max in 0..=2 {
+                for prefix in [false, true] {
+                    let expected = index
+                        .iter()
+                        .filter_map(|key| {
+                            let key_chars = key.chars().collect::<Vec<_>>();
+                            osa_distance(&query, &key_chars, max, prefix, |a, b| {
+                                a.eq_ignore_ascii_case(&b)
+                            })
+                            .map(|distance| (key.as_str(), distance))
+                        })
+                        .collect::<Vec<_>>();
+                    assert_eq!(search_key_index(&index, &query, max, prefix), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_search_key_index_by_whole_key() {
+        const KEYS: [&str; 6] = [
+            "long hair",
+            "Long hair",
+            "blonde hair",
+            "smile",
+            "ロングヘア",
+            "xyz",
+        ];
+        let found = |query, max| search(&KEYS, query, max, false);
+        assert_eq!(
+            found("long hair", 2),
+            vec![("Long hair", 0), ("long hair", 0)]
+        );
+        assert_eq!(
+            found("lnog hair", 2),
+            vec![("Long hair", 1), ("long hair", 1)]
+        );
+        assert_eq!(found("blonde", 2), vec![]);
+        assert_eq!(found("smiel", 1), vec![("smile", 1)]);
+        assert_eq!(found("ロンゲヘア", 1), vec![("ロングヘア", 1)]);
+        assert_eq!(found("abc", 2), vec![]);
+    }
+
+    #[test]
+    fn test_typo_fallback() {
+        let mut engine = DictionaryEngine::new(create_test_csv_data());
+
+        let results = engine.fuzzy_search("lnog_hair", None, None);
+        assert_eq!(results[0].term, "long_hair");
+        assert!(results[0].is_typo_correction);
+
+        let results = engine.fuzzy_search("blonf", None, None);
+        let blonde = results.iter().find(|r| r.term == "blonde_hair").unwrap();
+        assert!(blonde.is_typo_correction);
+
+        // Primary matches are not flagged, and outrank corrections
+        let results = engine.fuzzy_search("long", None, None);
+        assert!(!results[0].is_typo_correction);
+        assert!(
+            results
+                .iter()
+                .skip_while(|r| !r.is_typo_correction)
+                .all(|r| r.is_typo_correction)
+        );
+
+        // Disabled fallback returns no corrections
+        let results = engine.fuzzy_search_with_options(
+            "lnog_hair",
+            Some(FuzzySearchOptions {
+                typo_fallback_threshold: Some(0),
+                ..Default::default()
+            }),
+        );
+        assert!(results.iter().all(|r| !r.is_typo_correction));
+    }
+
+    #[test]
+    fn test_typo_corrections_rank_after_matches() {
+        let mut engine = DictionaryEngine::from_sources(
+            vec![
+                DictionarySourceInput {
+                    id: "base".to_string(),
+                    text: "lnog_coat,0,1,\n".to_string(),
+                    ..Default::default()
+                },
+                DictionarySourceInput {
+                    id: "user".to_string(),
+                    text: "long,0,100000,\n".to_string(),
+                    priority: 10,
+                    ..Default::default()
+                },
+            ],
+            None,
+        );
+
+        // The correction from the prioritized source has the higher rank, yet comes last
+        let results = engine.fuzzy_search("lnog", None, None);
+        let terms = results
+            .iter()
+            .map(|r| (r.term.as_str(), r.is_typo_correction))
+            .collect::<Vec<_>>();
+        assert_eq!(terms, vec![("lnog_coat", false), ("long", true)]);
+        assert!(results[1].rank > results[0].rank);
+    }
+}
@@ -0,0 +1,60 @@
+const SYLLABLE_BASE: u32 = 0xAC00;
+const SYLLABLE_COUNT: u32 = 11172;
+// Number of syllables sharing an initial consonant (21 vowels * 28 finals)
+const SYLLABLES_PER_INITIAL: u32 = 588;
+
+// Initial consonants in syllable order, as compatibility jamo
+const INITIALS: [char; 19] = [
+    'ㄱ', 'ㄲ', 'ㄴ', 'ㄷ', 'ㄸ', 'ㄹ', 'ㅁ', 'ㅂ', 'ㅃ', 'ㅅ', 'ㅆ', 'ㅇ', 'ㅈ', 'ㅉ', 'ㅊ', 'ㅋ',
+    'ㅌ', 'ㅍ', 'ㅎ',
+];
+
+fn initial_of(c: char) -> Option<char> {
+    let index = (c as u32).checked_sub(SYLLABLE_BASE)?;
+    if index >= SYLLABLE_COUNT {
+        return None;
+    }
+    Some(INITIALS[(index / SYLLABLES_PER_INITIAL) as usize])
+}
+
+// The initial consonants (초성) of Hangul syllables, e.g. `ㄷㅂ` for `단발`. ASCII characters are kept as is.
+// Returns `None` if the text has no Hangul syllables, or has any other character.
+pub fn to_initial_consonants(text: &str) -> Option<String> {
+    let mut initials = String::with_capacity(text.len());
+    let mut has_hangul = false;
+
+    for c in text.chars() {
+        if c.is_ascii() {
+            initials.push(c);
+            continue;
+        }
+        initials.push(initial_of(c)?);
+        has_hangul = true;
+    }
+
+    has_hangul.then_some(initials)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_initial_consonants() {
+        assert_eq!(to_initial_consonants("단발").as_deref(), Some("ㄷㅂ"));
+        assert_eq!(to_initial_consonants("소녀").as_deref(), Some("ㅅㄴ"));
+        assert_eq!(
+            to_initial_consonants("금발벽안").as_deref(),
+            Some("ㄱㅂㅂㅇ")
+        );
+        assert_eq!(to_initial_consonants("가힣").as_deref(), Some("ㄱㅎ"));
+        assert_eq!(to_initial_consonants("1 소녀").as_deref(), Some("1 ㅅㄴ"));
+    }
+
+    #[test]
+    fn test_to_initial_consonants_unsupported_text() {
+        assert_eq!(to_initial_consonants("girl"), None);
+        assert_eq!(to_initial_consonants("소녀ちゃん"), None);
+        assert_eq!(to_initial_consonants("ㄷㅂ"), None);
+    }
+}
@@ -0,0 +1,41 @@
+use pinyin::ToPinyin;
+
+// Toneless pinyin of a text, with `ü` written as `v` the way pinyin input methods expect it.
+// ASCII characters are kept as is. Returns `None` if the text has no Han characters, or has any
+// other character without a reading.
+pub fn to_pinyin(text: &str) -> Option<String> {
+    let mut pinyin = String::with_capacity(text.len() * 2);
+    let mut has_han = false;
+
+    for c in text.chars() {
+        if c.is_ascii() {
+            pinyin.push(c);
+            continue;
+        }
+        let reading = c.to_pinyin()?.plain();
+        pinyin.extend(reading.chars().map(|c| if c == 'ü' { 'v' } else { c }));
+        has_han = true;
+    }
+
+    has_han.then_some(pinyin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_pinyin() {
+        assert_eq!(to_pinyin("黑发").as_deref(), Some("heifa"));
+        assert_eq!(to_pinyin("金发碧眼").as_deref(), Some("jinfabiyan"));
+        assert_eq!(to_pinyin("女孩").as_deref(), Some("nvhai"));
+        assert_eq!(to_pinyin("3d 模型").as_deref(), Some("3d moxing"));
+    }
+
+    #[test]
+    fn test_to_pinyin_unsupported_text() {
+        assert_eq!(to_pinyin("hair"), None);
+        assert_eq!(to_pinyin("長髪けもフレ"), None);
+        assert_eq!(to_pinyin("소녀"), None);
+    }
+}
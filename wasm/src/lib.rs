@@ -1,6 +1,11 @@
 mod coding;
 mod dictionary_engine;
+mod hangul;
+mod hanzi;
 mod normalize;
+mod prompt;
+mod romaji;
+mod script;
 
 pub use coding::*;
 pub use dictionary_engine::*;
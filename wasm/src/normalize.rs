@@ -1,4 +1,4 @@
-use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::{UnicodeNormalization, char::canonical_combining_class};
 
 pub fn normalize_for_auto_completion(text: &str) -> String {
     text.nfkc()
@@ -13,6 +13,31 @@ pub fn normalize_for_auto_completion(text: &str) -> String {
         .collect()
 }
 
+// Like `normalize_for_auto_completion`, also returning for each output char the index of the input char
+// it came from. Each starter is normalized together with the combining marks following it.
+pub fn normalize_for_auto_completion_with_offsets(text: &str) -> (Vec<char>, Vec<u32>) {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut normalized = Vec::with_capacity(chars.len());
+    let mut offsets = Vec::with_capacity(chars.len());
+
+    let mut start = 0;
+    while start < chars.len() {
+        let mut end = start + 1;
+        while end < chars.len() && canonical_combining_class(chars[end]) != 0 {
+            end += 1;
+        }
+
+        let segment = chars[start..end].iter().collect::<String>();
+        for c in normalize_for_auto_completion(&segment).chars() {
+            normalized.push(c);
+            offsets.push(start as u32);
+        }
+        start = end;
+    }
+
+    (normalized, offsets)
+}
+
 pub fn normalize_for_query(text: &str) -> String {
     text.nfc()
         .map(|c| match c {
@@ -129,6 +154,23 @@ mod tests {
         assert_eq!(normalize_for_auto_completion("\n"), "\n");
     }
 
+    #[test]
+    fn test_normalize_for_auto_completion_with_offsets() {
+        let (normalized, offsets) = normalize_for_auto_completion_with_offsets("ロング_hair");
+        assert_eq!(normalized.iter().collect::<String>(), "ろんぐ hair");
+        assert_eq!(offsets, vec![0, 1, 2, 3, 4, 5, 6, 7]);
+
+        // Expansions map every output char to the same input char
+        let (normalized, offsets) = normalize_for_auto_completion_with_offsets("a㍻b");
+        assert_eq!(normalized.iter().collect::<String>(), "a平成b");
+        assert_eq!(offsets, vec![0, 1, 1, 2]);
+
+        // Combining marks are composed with their starter
+        let (normalized, offsets) = normalize_for_auto_completion_with_offsets("cafe\u{301}_x");
+        assert_eq!(normalized.iter().collect::<String>(), "café x");
+        assert_eq!(offsets, vec![0, 1, 2, 3, 5, 6]);
+    }
+
     #[test]
     fn test_normalize_for_query_basic() {
         // Test basic ASCII text
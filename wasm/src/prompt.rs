@@ -0,0 +1,333 @@
+use std::ops::Range;
+
+// Weight multipliers of emphasis brackets, mirroring `parseNormalTag` in `src/lib/core/tokenizer.ts`
+const PARENTHESES_FACTOR: f64 = 1.1;
+const BRACKETS_FACTOR: f64 = 0.9;
+
+// A tag fragment of a prompt with its emphasis and weight syntax peeled off
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeightedTag {
+    // The bare name, with backslash escapes removed
+    pub name: String,
+    pub weight: f64,
+    // Byte range of the name within the fragment, before unescaping
+    pub name_range: Range<usize>,
+}
+
+// Whether the char at the byte position is preceded by an odd number of backslashes
+fn is_escaped(text: &str, position: usize) -> bool {
+    text.as_bytes()[..position]
+        .iter()
+        .rev()
+        .take_while(|&&b| b == b'\\')
+        .count()
+        % 2
+        == 1
+}
+
+// The `:weight` suffix of `name:weight`, returning the length of the name and the weight
+fn explicit_weight(text: &str) -> Option<(usize, f64)> {
+    let colon = text.rfind(':')?;
+    let number = &text[colon + 1..];
+    let digits = number.strip_prefix('-').unwrap_or(number);
+    let (integer, fraction) = digits.split_once('.').unwrap_or((digits, "0"));
+    let is_number = !integer.is_empty()
+        && !fraction.is_empty()
+        && integer
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|b| b.is_ascii_digit());
+    if colon == 0 || !is_number {
+        return None;
+    }
+    number.parse().ok().map(|weight| (colon, weight))
+}
+
+// Removes backslash escapes of brackets and backslashes, e.g. `artist_\(style\)` to `artist_(style)`
+pub fn unescape_brackets(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\'
+            && let Some(&next) = chars.peek()
+            && matches!(next, '(' | ')' | '[' | ']' | '{' | '}' | '\\')
+        {
+            unescaped.push(next);
+            chars.next();
+        } else {
+            unescaped.push(c);
+        }
+    }
+    unescaped
+}
+
+// Escapes parentheses with backslashes, like the `parentheses` escape target of `src/lib/core/compile.ts`
+pub fn escape_parentheses(text: &str) -> String {
+    text.replace('(', "\\(").replace(')', "\\)")
+}
+
+// Parses a single tag fragment like `(tag:1.2)`, `[[tag]]`, `-tag` or `artist_\(style\)`.
+// `-` sets the weight to 0, each `(` multiplies it by 1.1 and each `[` by 0.9, and an explicit
+// `:weight` inside parentheses multiplies it by that weight. Escaped brackets are part of the name.
+pub fn parse_weighted_tag(fragment: &str) -> WeightedTag {
+    let mut start = fragment.len() - fragment.trim_start().len();
+    let mut end = fragment.trim_end().len().max(start);
+    let mut weight = 1.0;
+
+    if fragment[start..end].starts_with('-') {
+        weight = 0.0;
+        start += 1;
+    }
+
+    loop {
+        let text = &fragment[start..end];
+        let (close, factor) = match text.as_bytes().first() {
+            Some(b'(') => (')', PARENTHESES_FACTOR),
+            Some(b'[') => (']', BRACKETS_FACTOR),
+            _ => break,
+        };
+        if text.len() < 2 || !text.ends_with(close) || is_escaped(text, text.len() - 1) {
+            break;
+        }
+        start += 1;
+        end -= 1;
+
+        match explicit_weight(&fragment[start..end]).filter(|_| close == ')') {
+            Some((name_len, explicit)) => {
+                weight *= explicit;
+                end = start + name_len;
+            }
+            None => weight *= factor,
+        }
+
+        // Whitespace inside the brackets is not part of the name
+        let inner = &fragment[start..end];
+        start += inner.len() - inner.trim_start().len();
+        end = start + inner.trim().len();
+    }
+
+    WeightedTag {
+        name: unescape_brackets(&fragment[start..end]),
+        weight,
+        name_range: start..end,
+    }
+}
+
+// A tag token of a prompt. Chants, LoRAs and embeddings are not tag tokens.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagToken {
+    // Byte range of the trimmed token within the prompt
+    pub range: Range<usize>,
+    // The parsed tag, with `name_range` relative to the prompt
+    pub tag: WeightedTag,
+}
+
+// Byte ranges of `//` and `/* */` comments, mirroring `findCommentRanges` in `src/lib/core/comment.ts`.
+// Ranges of the two kinds may overlap.
+pub fn comment_ranges(text: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+
+    let mut position = 0;
+    while let Some(offset) = text[position..].find("//") {
+        let start = position + offset;
+        let end = text[start..]
+            .find('\n')
+            .map_or(text.len(), |end| start + end);
+        ranges.push(start..end);
+        position = end;
+    }
+
+    let mut position = 0;
+    while let Some(offset) = text[position..].find("/*") {
+        let start = position + offset;
+        // Unclosed comments are not comments
+        let Some(end) = text[start + 2..].find("*/") else {
+            break;
+        };
+        let end = start + 2 + end + 2;
+        ranges.push(start..end);
+        position = end;
+    }
+
+    ranges.sort_by_key(|range| range.start);
+    ranges
+}
+
+// Replaces comments by spaces, keeping the byte offsets of everything else
+pub fn strip_comments(text: &str) -> String {
+    let mut bytes = text.as_bytes().to_vec();
+    for range in comment_ranges(text) {
+        bytes[range].fill(b' ');
+    }
+    // Comments start and end on char boundaries, so only whole chars were replaced
+    String::from_utf8(bytes).unwrap_or_default()
+}
+
+// Whether a token is a chant marker or reference, a LoRA or an embedding rather than a tag
+fn is_special_token(token: &str) -> bool {
+    let token = token.strip_prefix('-').unwrap_or(token);
+    token.starts_with('@') || token.starts_with("<lora:") || token.starts_with("<embedding:")
+}
+
+// The tag tokens of a prompt, separated by commas and newlines, like `findTokenRanges` in
+// `src/lib/core/tokenizer.ts` applied to the prompt without comments
+pub fn tag_tokens(text: &str) -> Vec<TagToken> {
+    let stripped = strip_comments(text);
+
+    let mut tokens = Vec::new();
+    let mut position = 0;
+    for segment in stripped.split([',', '\n']) {
+        let start = position + (segment.len() - segment.trim_start().len());
+        let end = position + segment.trim_end().len();
+        position += segment.len() + 1;
+
+        if start >= end || is_special_token(&stripped[start..end]) {
+            continue;
+        }
+        let mut tag = parse_weighted_tag(&stripped[start..end]);
+        tag.name_range = start + tag.name_range.start..start + tag.name_range.end;
+        tokens.push(TagToken {
+            range: start..end,
+            tag,
+        });
+    }
+    tokens
+}
+
+// Converts increasing byte offsets of a text to UTF-16 offsets, as used by JavaScript strings
+pub struct Utf16Cursor<'a> {
+    text: &'a str,
+    byte: usize,
+    utf16: usize,
+}
+
+impl<'a> Utf16Cursor<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Utf16Cursor {
+            text,
+            byte: 0,
+            utf16: 0,
+        }
+    }
+
+    // The UTF-16 offset of a byte offset, which must not be before the previous one
+    pub fn offset(&mut self, byte: usize) -> u32 {
+        self.utf16 += self.text[self.byte..byte]
+            .chars()
+            .map(char::len_utf16)
+            .sum::<usize>();
+        self.byte = byte;
+        self.utf16 as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(fragment: &str) -> (String, f64) {
+        let tag = parse_weighted_tag(fragment);
+        (tag.name, (tag.weight * 1000.0).round() / 1000.0)
+    }
+
+    #[test]
+    fn test_parse_weighted_tag() {
+        assert_eq!(parse("long_hair"), ("long_hair".to_string(), 1.0));
+        assert_eq!(parse(" (long_hair:1.2) "), ("long_hair".to_string(), 1.2));
+        assert_eq!(parse("((long_hair))"), ("long_hair".to_string(), 1.21));
+        assert_eq!(parse("[[long_hair]]"), ("long_hair".to_string(), 0.81));
+        assert_eq!(parse("-long_hair"), ("long_hair".to_string(), 0.0));
+        assert_eq!(parse("((long_hair:0.5))"), ("long_hair".to_string(), 0.55));
+        assert_eq!(parse("( long hair )"), ("long hair".to_string(), 1.1));
+        assert_eq!(parse("[tag:1.5]"), ("tag:1.5".to_string(), 0.9));
+        assert_eq!(parse("re:zero"), ("re:zero".to_string(), 1.0));
+    }
+
+    #[test]
+    fn test_parse_escaped_tag() {
+        assert_eq!(
+            parse("artist_\\(style\\)"),
+            ("artist_(style)".to_string(), 1.0)
+        );
+        assert_eq!(
+            parse("(artist_\\(style\\):1.3)"),
+            ("artist_(style)".to_string(), 1.3)
+        );
+        assert_eq!(parse("\\(tag\\)"), ("(tag)".to_string(), 1.0));
+        // Unbalanced brackets are left alone
+        assert_eq!(parse("(tag"), ("(tag".to_string(), 1.0));
+        assert_eq!(parse("tag)"), ("tag)".to_string(), 1.0));
+    }
+
+    #[test]
+    fn test_name_range() {
+        let fragment = " ((artist_\\(style\\):1.2)) ";
+        let tag = parse_weighted_tag(fragment);
+        assert_eq!(&fragment[tag.name_range], "artist_\\(style\\)");
+        assert_eq!(parse_weighted_tag("").name_range, 0..0);
+        assert_eq!(parse_weighted_tag("()").name, "");
+    }
+
+    #[test]
+    fn test_escape_parentheses() {
+        assert_eq!(escape_parentheses("artist_(style)"), "artist_\\(style\\)");
+        assert_eq!(
+            unescape_brackets(&escape_parentheses("a(b)")),
+            "a(b)".to_string()
+        );
+    }
+
+    #[test]
+    fn test_unescape_brackets() {
+        assert_eq!(unescape_brackets("a\\(b\\)\\[c\\]"), "a(b)[c]");
+        assert_eq!(unescape_brackets("a\\\\b"), "a\\b");
+        assert_eq!(unescape_brackets("a\\b"), "a\\b");
+    }
+
+    #[test]
+    fn test_comment_ranges() {
+        let text = "a, // b, c\nd /* e,\nf */ g /* h";
+        let ranges = comment_ranges(text);
+        assert_eq!(
+            ranges.iter().map(|r| &text[r.clone()]).collect::<Vec<_>>(),
+            vec!["// b, c", "/* e,\nf */"]
+        );
+        assert_eq!(strip_comments(text).len(), text.len());
+        assert_eq!(
+            strip_comments("金髪 // 長髪"),
+            format!("金髪{}", " ".repeat(10))
+        );
+    }
+
+    #[test]
+    fn test_tag_tokens() {
+        let text = "masterpiece, (long_hair:1.2)\n@chant, @@marker, <lora:foo:0.8>, -<embedding:bar>,\n  // smile, 1girl\n[[金髪]] /* x, y */ , -blue_eyes";
+        let tokens = tag_tokens(text);
+        let names = tokens
+            .iter()
+            .map(|t| (&text[t.range.clone()], t.tag.name.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                ("masterpiece", "masterpiece"),
+                ("(long_hair:1.2)", "long_hair"),
+                ("[[金髪]]", "金髪"),
+                ("-blue_eyes", "blue_eyes"),
+            ]
+        );
+        assert_eq!(&text[tokens[1].tag.name_range.clone()], "long_hair");
+        assert_eq!(tokens[3].tag.weight, 0.0);
+    }
+
+    #[test]
+    fn test_utf16_cursor() {
+        let text = "a😀金b";
+        let mut cursor = Utf16Cursor::new(text);
+        assert_eq!(cursor.offset(0), 0);
+        assert_eq!(cursor.offset(1), 1);
+        assert_eq!(cursor.offset(5), 3);
+        assert_eq!(cursor.offset(8), 4);
+        assert_eq!(cursor.offset(9), 5);
+    }
+}
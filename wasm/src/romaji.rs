@@ -0,0 +1,245 @@
+// Romanization systems produced by `kana_to_romaji`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RomajiSystem {
+    // shi, chi, tsu, fu, ji, sha
+    Hepburn,
+    // si, ti, tu, hu, zi, sya
+    Kunrei,
+}
+
+// Romaji of a single hiragana, as (Hepburn, Kunrei)
+fn syllable(c: char) -> Option<(&'static str, &'static str)> {
+    let romaji = match c {
+        'あ' | 'ぁ' => ("a", "a"),
+        'い' | 'ぃ' => ("i", "i"),
+        'う' | 'ぅ' => ("u", "u"),
+        'え' | 'ぇ' => ("e", "e"),
+        'お' | 'ぉ' => ("o", "o"),
+        'か' | 'ゕ' => ("ka", "ka"),
+        'き' => ("ki", "ki"),
+        'く' => ("ku", "ku"),
+        'け' | 'ゖ' => ("ke", "ke"),
+        'こ' => ("ko", "ko"),
+        'が' => ("ga", "ga"),
+        'ぎ' => ("gi", "gi"),
+        'ぐ' => ("gu", "gu"),
+        'げ' => ("ge", "ge"),
+        'ご' => ("go", "go"),
+        'さ' => ("sa", "sa"),
+        'し' => ("shi", "si"),
+        'す' => ("su", "su"),
+        'せ' => ("se", "se"),
+        'そ' => ("so", "so"),
+        'ざ' => ("za", "za"),
+        'じ' => ("ji", "zi"),
+        'ず' => ("zu", "zu"),
+        'ぜ' => ("ze", "ze"),
+        'ぞ' => ("zo", "zo"),
+        'た' => ("ta", "ta"),
+        'ち' => ("chi", "ti"),
+        'つ' => ("tsu", "tu"),
+        'て' => ("te", "te"),
+        'と' => ("to", "to"),
+        'だ' => ("da", "da"),
+        'ぢ' => ("ji", "zi"),
+        'づ' => ("zu", "zu"),
+        'で' => ("de", "de"),
+        'ど' => ("do", "do"),
+        'な' => ("na", "na"),
+        'に' => ("ni", "ni"),
+        'ぬ' => ("nu", "nu"),
+        'ね' => ("ne", "ne"),
+        'の' => ("no", "no"),
+        'は' => ("ha", "ha"),
+        'ひ' => ("hi", "hi"),
+        'ふ' => ("fu", "hu"),
+        'へ' => ("he", "he"),
+        'ほ' => ("ho", "ho"),
+        'ば' => ("ba", "ba"),
+        'び' => ("bi", "bi"),
+        'ぶ' => ("bu", "bu"),
+        'べ' => ("be", "be"),
+        'ぼ' => ("bo", "bo"),
+        'ぱ' => ("pa", "pa"),
+        'ぴ' => ("pi", "pi"),
+        'ぷ' => ("pu", "pu"),
+        'ぺ' => ("pe", "pe"),
+        'ぽ' => ("po", "po"),
+        'ま' => ("ma", "ma"),
+        'み' => ("mi", "mi"),
+        'む' => ("mu", "mu"),
+        'め' => ("me", "me"),
+        'も' => ("mo", "mo"),
+        'や' | 'ゃ' => ("ya", "ya"),
+        'ゆ' | 'ゅ' => ("yu", "yu"),
+        'よ' | 'ょ' => ("yo", "yo"),
+        'ら' => ("ra", "ra"),
+        'り' => ("ri", "ri"),
+        'る' => ("ru", "ru"),
+        'れ' => ("re", "re"),
+        'ろ' => ("ro", "ro"),
+        'わ' | 'ゎ' => ("wa", "wa"),
+        'ゐ' => ("i", "i"),
+        'ゑ' => ("e", "e"),
+        'を' => ("o", "o"),
+        'ん' => ("n", "n"),
+        'ゔ' => ("vu", "vu"),
+        _ => return None,
+    };
+    Some(romaji)
+}
+
+fn is_small_y(c: char) -> bool {
+    matches!(c, 'ゃ' | 'ゅ' | 'ょ')
+}
+
+fn is_small_vowel(c: char) -> bool {
+    matches!(c, 'ぁ' | 'ぃ' | 'ぅ' | 'ぇ' | 'ぉ')
+}
+
+// Transliterates hiragana into romaji. ASCII characters are kept as is, long vowel marks are dropped
+// and a sokuon doubles the following consonant. Returns `None` if the text has any other character.
+pub fn kana_to_romaji(text: &str, system: RomajiSystem) -> Option<String> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut romaji = String::with_capacity(text.len() * 2);
+    let mut sokuon = false;
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+
+        if c.is_ascii() {
+            romaji.push(c);
+            sokuon = false;
+            continue;
+        }
+        match c {
+            'ー' | '〜' => continue,
+            'っ' => {
+                sokuon = true;
+                continue;
+            }
+            _ => {}
+        }
+
+        let (hepburn, kunrei) = syllable(c)?;
+        let mut syllable = match system {
+            RomajiSystem::Hepburn => hepburn,
+            RomajiSystem::Kunrei => kunrei,
+        }
+        .to_string();
+
+        // Contracted sounds such as きゃ, しゃ or ふぁ
+        if let Some(&next) = chars.get(i) {
+            if is_small_y(next) && syllable.len() > 1 && syllable.ends_with('i') {
+                syllable.pop();
+                if !(syllable.ends_with("sh") || syllable.ends_with("ch") || syllable == "j") {
+                    syllable.push('y');
+                }
+                syllable.push_str(&syllable_vowel(next));
+                i += 1;
+            } else if is_small_vowel(next) && syllable.len() > 1 {
+                syllable.pop();
+                syllable.push_str(&syllable_vowel(next));
+                i += 1;
+            }
+        }
+
+        if sokuon {
+            sokuon = false;
+            if syllable.starts_with("ch") {
+                romaji.push('t');
+            } else if let Some(first) = syllable.chars().next()
+                && !"aiueon".contains(first)
+            {
+                romaji.push(first);
+            }
+        }
+        romaji.push_str(&syllable);
+    }
+
+    Some(romaji)
+}
+
+fn syllable_vowel(c: char) -> String {
+    syllable(c)
+        .map(|(hepburn, _)| hepburn.chars().last().unwrap_or_default().to_string())
+        .unwrap_or_default()
+}
+
+// The distinct romaji spellings of a hiragana text, or nothing if it has no kana
+pub fn romaji_variants(text: &str) -> Vec<String> {
+    if !text.chars().any(|c| syllable(c).is_some() || c == 'っ') {
+        return Vec::new();
+    }
+
+    let mut variants = Vec::with_capacity(2);
+    for system in [RomajiSystem::Hepburn, RomajiSystem::Kunrei] {
+        if let Some(romaji) = kana_to_romaji(text, system)
+            && !variants.contains(&romaji)
+        {
+            variants.push(romaji);
+        }
+    }
+    variants
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hepburn(text: &str) -> Option<String> {
+        kana_to_romaji(text, RomajiSystem::Hepburn)
+    }
+
+    fn kunrei(text: &str) -> Option<String> {
+        kana_to_romaji(text, RomajiSystem::Kunrei)
+    }
+
+    #[test]
+    fn test_basic_syllables() {
+        assert_eq!(hepburn("ろんぐへあ").as_deref(), Some("ronguhea"));
+        assert_eq!(hepburn("ねこ").as_deref(), Some("neko"));
+        assert_eq!(hepburn("つしま").as_deref(), Some("tsushima"));
+        assert_eq!(kunrei("つしま").as_deref(), Some("tusima"));
+        assert_eq!(hepburn("ふじ").as_deref(), Some("fuji"));
+        assert_eq!(kunrei("ふじ").as_deref(), Some("huzi"));
+    }
+
+    #[test]
+    fn test_contracted_sounds() {
+        assert_eq!(hepburn("しゃしん").as_deref(), Some("shashin"));
+        assert_eq!(kunrei("しゃしん").as_deref(), Some("syasin"));
+        assert_eq!(hepburn("ちょこ").as_deref(), Some("choko"));
+        assert_eq!(kunrei("ちょこ").as_deref(), Some("tyoko"));
+        assert_eq!(hepburn("じゅう").as_deref(), Some("juu"));
+        assert_eq!(hepburn("きゃら").as_deref(), Some("kyara"));
+        assert_eq!(hepburn("ふぁん").as_deref(), Some("fan"));
+        assert_eq!(hepburn("てぃー").as_deref(), Some("ti"));
+    }
+
+    #[test]
+    fn test_sokuon_and_long_vowels() {
+        assert_eq!(hepburn("がっこう").as_deref(), Some("gakkou"));
+        assert_eq!(hepburn("まっちゃ").as_deref(), Some("matcha"));
+        assert_eq!(kunrei("まっちゃ").as_deref(), Some("mattya"));
+        assert_eq!(hepburn("ろんぐへあー").as_deref(), Some("ronguhea"));
+        assert_eq!(hepburn("あっ").as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn test_mixed_and_unsupported_text() {
+        assert_eq!(hepburn("ろんぐ hair").as_deref(), Some("rongu hair"));
+        assert_eq!(hepburn("黒髪ろんぐ"), None);
+        assert_eq!(hepburn("소녀"), None);
+    }
+
+    #[test]
+    fn test_romaji_variants() {
+        assert_eq!(romaji_variants("ねこ"), vec!["neko"]);
+        assert_eq!(romaji_variants("しっぽ"), vec!["shippo", "sippo"]);
+        assert!(romaji_variants("cat").is_empty());
+        assert!(romaji_variants("黒髪").is_empty());
+    }
+}
@@ -0,0 +1,104 @@
+// Scripts of CJK text, as far as tag aliases need to be told apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Script {
+    Kana,
+    Han,
+    Hangul,
+}
+
+fn char_script(c: char) -> Option<Script> {
+    match c {
+        '\u{3040}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}' | '\u{FF66}'..='\u{FF9F}' => {
+            Some(Script::Kana)
+        }
+        '\u{3005}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{2FA1F}' => Some(Script::Han),
+        '\u{1100}'..='\u{11FF}' | '\u{3130}'..='\u{318F}' | '\u{AC00}'..='\u{D7A3}' => {
+            Some(Script::Hangul)
+        }
+        _ => None,
+    }
+}
+
+// The script of a text: `Kana` if it has any kana, `Hangul` if it has any Hangul, `Han` if its only
+// CJK characters are Han, and `None` without CJK characters
+pub fn text_script(text: &str) -> Option<Script> {
+    let mut script = None;
+    for c in text.chars() {
+        match char_script(c) {
+            Some(Script::Kana) => return Some(Script::Kana),
+            Some(Script::Hangul) => return Some(Script::Hangul),
+            Some(Script::Han) => script = Some(Script::Han),
+            None => {}
+        }
+    }
+    script
+}
+
+// Common Han characters whose form tells the written language apart. The lists are not complete:
+// characters in none of them are written the same way in several languages, or are rare.
+// Simplified forms, and characters modern Japanese does not use
+const CHINESE_CHARS: &str = "发长蓝颜头红绿银鸟鱼马龙门风飞东车乐书时们这说话语见觉亲戏剧单丝袜带领结饰环链锁镜脸兽战剑枪弹动电脑视线裤衬连罗爱娇妆齿紧闭张开举扬肤辫无黑孩吗呢吧么伞项胶护过还让从样边难变实兴图员团园坏对钱铁错闪阴阳队际离卫买卖读谁请认帮鸡鸭宠饭汤热冻泽睁脖颈汉韩质层级纸织给细组经绘纱绳网丽烟军舰凤鹰树兰樱莲苹气岁儿妇纯专业间问闻关页顶须顺预题额饮馆验骑鲜鸣齐龟";
+// Traditional forms, written differently in both Simplified Chinese and Japanese
+const TRADITIONAL_CHARS: &str = "髮顏藍發國學體會點戰聽說話語綠樂們這覺戲單絲襪帶鏈臉獸劍彈腦褲襯妝齒舉辮嗎麼雙裝圖團對歲櫻氣兒專關驗齊龜鐵錢讀賣戀邊實變從樣轉傳聲處燒繪經輕雜應壓惡亞廣醫淚顯濕";
+// Japanese forms (shinjitai) and characters made in Japan
+const JAPANESE_CHARS: &str = "髪黒顔亜悪圧囲栄塩応桜仮価絵拡覚楽気帰挙駆経軽鶏県剣険験広効歳剤雑歯児実釈収従渋獣縦処焼証乗剰畳嬢譲醸粋酔穂髄摂専戦銭繊捜挿巣総騒蔵臓続対帯滝択沢単団弾遅昼鋳庁聴鎮鉄転伝稲闘徳読悩脳廃売発抜浜払仏変歩豊毎満黙薬訳揺様頼覧竜両猟緑涙塁暦歴労録込働峠畑匂枠咲辺図円関戸斎姫隠";
+
+// The written language a Han character belongs to, as far as its form tells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HanForm {
+    Chinese,
+    Traditional,
+    Japanese,
+}
+
+pub fn han_form(c: char) -> Option<HanForm> {
+    if CHINESE_CHARS.contains(c) {
+        Some(HanForm::Chinese)
+    } else if TRADITIONAL_CHARS.contains(c) {
+        Some(HanForm::Traditional)
+    } else if JAPANESE_CHARS.contains(c) {
+        Some(HanForm::Japanese)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_script() {
+        assert_eq!(text_script("ロングヘアー"), Some(Script::Kana));
+        assert_eq!(text_script("女の子"), Some(Script::Kana));
+        assert_eq!(text_script("金髪ロング"), Some(Script::Kana));
+        assert_eq!(text_script("長髪"), Some(Script::Han));
+        assert_eq!(text_script("3d模型"), Some(Script::Han));
+        assert_eq!(text_script("파란눈"), Some(Script::Hangul));
+        assert_eq!(text_script("ㄷㅂ"), Some(Script::Hangul));
+        assert_eq!(text_script("long_hair"), None);
+        assert_eq!(text_script("café"), None);
+    }
+
+    #[test]
+    fn test_han_form() {
+        assert_eq!(han_form('发'), Some(HanForm::Chinese));
+        assert_eq!(han_form('髮'), Some(HanForm::Traditional));
+        assert_eq!(han_form('髪'), Some(HanForm::Japanese));
+        assert_eq!(han_form('長'), None);
+        assert_eq!(han_form('a'), None);
+
+        let lists = [CHINESE_CHARS, TRADITIONAL_CHARS, JAPANESE_CHARS];
+        for (i, list) in lists.iter().enumerate() {
+            for c in list.chars() {
+                assert_eq!(char_script(c), Some(Script::Han), "{c}");
+                assert_eq!(list.matches(c).count(), 1, "{c}");
+                assert!(lists[i + 1..].iter().all(|other| !other.contains(c)), "{c}");
+            }
+        }
+    }
+}